@@ -147,6 +147,12 @@ pub enum Key {
     TxnByHash([u8; 32]),
     StoreVersion,
     NonEmptyBlock(KeyNonEmptyBlock),
+    LeafToTxn([u8; 32]),
+    PrunedHeight,
+    RejectedTxn([u8; 32]),
+    RejectedTxnBySeq(u64),
+    NextRejectedTxnSeq,
+    OldestRejectedTxnSeq,
 }
 
 impl Key {
@@ -159,6 +165,12 @@ impl Key {
             Self::TxnByHash(_) => 4,
             Self::StoreVersion => 5,
             Self::NonEmptyBlock(_) => 6,
+            Self::LeafToTxn(_) => 7,
+            Self::PrunedHeight => 8,
+            Self::RejectedTxn(_) => 9,
+            Self::RejectedTxnBySeq(_) => 10,
+            Self::NextRejectedTxnSeq => 11,
+            Self::OldestRejectedTxnSeq => 12,
         }
     }
 
@@ -181,6 +193,18 @@ impl Key {
             Self::NonEmptyBlock(block_number) => {
                 block_number.serialize_to(&mut out);
             }
+            Self::LeafToTxn(leaf) => {
+                out.extend_from_slice(leaf);
+            }
+            Self::PrunedHeight => {}
+            Self::RejectedTxn(txn_hash) => {
+                out.extend_from_slice(txn_hash);
+            }
+            Self::RejectedTxnBySeq(seq) => {
+                out.extend_from_slice(&seq.to_be_bytes());
+            }
+            Self::NextRejectedTxnSeq => {}
+            Self::OldestRejectedTxnSeq => {}
         }
 
         out
@@ -207,6 +231,25 @@ impl Key {
             }
             5 => Ok(Self::StoreVersion),
             6 => KeyNonEmptyBlock::deserialize(bytes).map(Self::NonEmptyBlock),
+            7 => {
+                let mut leaf = [0u8; 32];
+                leaf.copy_from_slice(&bytes[0..32]);
+                Ok(Self::LeafToTxn(leaf))
+            }
+            8 => Ok(Self::PrunedHeight),
+            9 => {
+                let mut txn_hash = [0u8; 32];
+                txn_hash.copy_from_slice(&bytes[0..32]);
+                Ok(Self::RejectedTxn(txn_hash))
+            }
+            10 => {
+                let Ok(seq) = TryInto::<[u8; 8]>::try_into(&bytes[0..8]) else {
+                    return Err(Error::InvalidKey);
+                };
+                Ok(Self::RejectedTxnBySeq(u64::from_be_bytes(seq)))
+            }
+            11 => Ok(Self::NextRejectedTxnSeq),
+            12 => Ok(Self::OldestRejectedTxnSeq),
             _ => Err(Error::InvalidKey),
         }
     }
@@ -8,7 +8,7 @@ mod keys;
 mod list;
 mod migration;
 
-use std::{marker::PhantomData, path::Path};
+use std::{marker::PhantomData, path::Path, sync::Mutex};
 
 use keys::{Key, KeyBlock, StoreKey};
 use migration::LATEST_VERSION;
@@ -41,6 +41,8 @@ type Result<T, E = Error> = std::result::Result<T, E>;
 
 pub struct BlockStore<B> {
     db: DB,
+    /// Held while recording a rejected txn, so concurrent rejections get distinct sequence numbers
+    rejected_txns_lock: Mutex<()>,
     _marker: PhantomData<B>,
 }
 
@@ -55,6 +57,9 @@ pub trait Block {
 
 pub trait Transaction {
     fn txn_hash(&self) -> [u8; 32];
+
+    /// The leaves created or spent by this txn, each is indexed to find the txn by leaf
+    fn leaves(&self) -> Vec<[u8; 32]>;
 }
 
 impl<B> BlockStore<B>
@@ -81,6 +86,7 @@ where
 
         let self_ = Self {
             db,
+            rejected_txns_lock: Mutex::new(()),
             _marker: PhantomData,
         };
 
@@ -94,6 +100,7 @@ where
 
         Ok(Self {
             db,
+            rejected_txns_lock: Mutex::new(()),
             _marker: PhantomData,
         })
     }
@@ -123,6 +130,10 @@ where
             batch.put(k.serialize(), v);
         }
 
+        for (k, v) in Self::leaf_entries(block) {
+            batch.put(k.serialize(), v);
+        }
+
         if let Some(key) = keys::KeyNonEmptyBlock::from_block(block) {
             batch.put(key.to_key().serialize(), block.to_bytes()?);
         }
@@ -139,6 +150,24 @@ where
             .map(move |tx| Ok((Key::TxnByHash(tx.txn_hash()), tx.to_bytes()?)))
     }
 
+    /// Index each leaf of each txn in the block to the (height, txn index) of the txn
+    fn leaf_entries(block: &B) -> impl Iterator<Item = (Key, Vec<u8>)> {
+        let height = block.block_height();
+
+        block
+            .txns()
+            .into_iter()
+            .enumerate()
+            .flat_map(move |(index, tx)| {
+                let mut location = height.to_be_bytes().to_vec();
+                location.extend_from_slice(&(index as u32).to_be_bytes());
+
+                tx.leaves()
+                    .into_iter()
+                    .map(move |leaf| (Key::LeafToTxn(leaf), location.clone()))
+            })
+    }
+
     pub fn get(&self, block_number: BlockHeight) -> Result<Option<B>> {
         let key = Key::Block(KeyBlock(block_number)).serialize();
 
@@ -189,6 +218,162 @@ where
         }
     }
 
+    /// Get the (height, index in block) of the txn that created or spent a leaf
+    pub fn get_txn_location_by_leaf(&self, leaf: [u8; 32]) -> Result<Option<(BlockHeight, u32)>> {
+        let key = Key::LeafToTxn(leaf);
+        let Some(bytes) = self.db.get(key.serialize())? else {
+            return Ok(None);
+        };
+
+        let (Ok(height), Ok(index)) = (
+            TryInto::<[u8; 8]>::try_into(&bytes[0..8]),
+            TryInto::<[u8; 4]>::try_into(&bytes[8..12]),
+        ) else {
+            return Err(Error::InvalidKey);
+        };
+
+        Ok(Some((
+            BlockHeight(u64::from_be_bytes(height)),
+            u32::from_be_bytes(index),
+        )))
+    }
+
+    /// Blocks below this height have been deleted by [`BlockStore::prune`]
+    pub fn get_pruned_height(&self) -> Result<Option<BlockHeight>> {
+        if let Some(height) = self.db.get(Key::PrunedHeight.serialize())? {
+            Ok(Some(BlockHeight(u64::from_be_bytes(
+                height.try_into().unwrap(),
+            ))))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Delete all blocks below `before`, along with their txn and leaf indexes
+    pub fn prune(&self, before: BlockHeight) -> Result<()> {
+        let from = self.get_pruned_height()?.unwrap_or(BlockHeight(0));
+        if before <= from {
+            return Ok(());
+        }
+
+        for block in self
+            .list(from..before, BlockListOrder::LowestToHighest)
+            .into_iterator()
+        {
+            let (_, block) = block?;
+
+            // Delete each block in its own batch, moving the pruned height along with it, so
+            // that the pruned height is accurate if we crash in the middle
+            let mut batch = rocksdb::WriteBatchWithTransaction::<false>::default();
+
+            batch.delete(Key::Block(KeyBlock(block.block_height())).serialize());
+            batch.delete(Key::BlockHashToHeight(block.block_hash()).serialize());
+            if let Some(key) = keys::KeyNonEmptyBlock::from_block(&block) {
+                batch.delete(key.to_key().serialize());
+            }
+
+            for txn in block.txns() {
+                batch.delete(Key::TxnByHash(txn.txn_hash()).serialize());
+            }
+
+            for (k, _) in Self::leaf_entries(&block) {
+                batch.delete(k.serialize());
+            }
+
+            batch.put(
+                Key::PrunedHeight.serialize(),
+                block.block_height().next().to_be_bytes(),
+            );
+
+            self.db.write(batch)?;
+        }
+
+        self.db
+            .put(Key::PrunedHeight.serialize(), before.to_be_bytes())?;
+
+        Ok(())
+    }
+
+    /// Record why a txn was rejected, replacing any previous rejection of the same txn. Only the
+    /// most recent `retain` rejections are kept, older ones are deleted
+    pub fn set_rejected_txn<R: WireMessage>(
+        &self,
+        txn_hash: [u8; 32],
+        rejection: &R,
+        retain: u64,
+    ) -> Result<()> {
+        let _guard = self.rejected_txns_lock.lock().unwrap();
+
+        let seq = match self.db.get(Key::NextRejectedTxnSeq.serialize())? {
+            Some(seq) => u64::from_be_bytes(seq.try_into().unwrap()),
+            None => 0,
+        };
+        // Sequence entries before this have already been deleted, so we don't scan over their
+        // tombstones on every rejection
+        let oldest_seq = match self.db.get(Key::OldestRejectedTxnSeq.serialize())? {
+            Some(seq) => u64::from_be_bytes(seq.try_into().unwrap()),
+            None => 0,
+        };
+
+        // The value is prefixed with its sequence number, so an old sequence entry for a txn that
+        // was rejected again doesn't delete the newer rejection
+        let mut value = seq.to_be_bytes().to_vec();
+        value.extend_from_slice(&rejection.to_bytes()?);
+
+        let mut batch = rocksdb::WriteBatchWithTransaction::<false>::default();
+        batch.put(Key::RejectedTxn(txn_hash).serialize(), value);
+        batch.put(Key::RejectedTxnBySeq(seq).serialize(), txn_hash);
+        batch.put(Key::NextRejectedTxnSeq.serialize(), (seq + 1).to_be_bytes());
+
+        let keep_from = (seq + 1).saturating_sub(retain).max(oldest_seq);
+        batch.put(
+            Key::OldestRejectedTxnSeq.serialize(),
+            keep_from.to_be_bytes(),
+        );
+
+        let start = Key::RejectedTxnBySeq(oldest_seq).serialize();
+        for entry in self.db.iterator(rocksdb::IteratorMode::From(
+            &start,
+            rocksdb::Direction::Forward,
+        )) {
+            let (key, old_txn_hash) = entry?;
+            let Key::RejectedTxnBySeq(old_seq) = Key::deserialize(&key)? else {
+                break;
+            };
+            if old_seq >= keep_from {
+                break;
+            }
+
+            batch.delete(&key);
+
+            let old_txn_hash: [u8; 32] =
+                old_txn_hash[..].try_into().map_err(|_| Error::InvalidKey)?;
+            if old_txn_hash == txn_hash {
+                continue;
+            }
+
+            let old_key = Key::RejectedTxn(old_txn_hash).serialize();
+            if let Some(old_value) = self.db.get(&old_key)? {
+                if old_value[..8] == old_seq.to_be_bytes() {
+                    batch.delete(old_key);
+                }
+            }
+        }
+
+        self.db.write(batch)?;
+
+        Ok(())
+    }
+
+    /// Get the rejection recorded by [`BlockStore::set_rejected_txn`], if it hasn't been deleted
+    pub fn get_rejected_txn<R: WireMessage>(&self, txn_hash: [u8; 32]) -> Result<Option<R>> {
+        match self.db.get(Key::RejectedTxn(txn_hash).serialize())? {
+            Some(value) if value.len() >= 8 => Ok(Some(R::from_bytes(&value[8..])?)),
+            Some(_) => Err(Error::InvalidKey),
+            None => Ok(None),
+        }
+    }
+
     fn store_version(&self) -> Result<u32> {
         if let Some(version) = self.db.get(Key::StoreVersion.serialize())? {
             Ok(u32::from_be_bytes(version.try_into().unwrap()))
@@ -233,6 +418,11 @@ mod tests {
         fn txn_hash(&self) -> [u8; 32] {
             *self.inner()
         }
+
+        // Use the hash as the only leaf, so txns can be looked up by leaf in tests
+        fn leaves(&self) -> Vec<[u8; 32]> {
+            vec![*self.inner()]
+        }
     }
 
     fn temp_dir() -> TempDir {
@@ -286,6 +476,12 @@ mod tests {
 
         assert_eq!(block_store.get_txn_by_hash([125; 32]).unwrap(), None);
 
+        assert_eq!(
+            block_store.get_txn_location_by_leaf([124; 32]).unwrap(),
+            Some((block_number, 1))
+        );
+        assert_eq!(block_store.get_txn_location_by_leaf([125; 32]).unwrap(), None);
+
         let listed_txns = block_store.list_txns().collect::<Result<Vec<_>>>().unwrap();
         assert_eq!(listed_txns, txns);
     }
@@ -362,6 +558,83 @@ mod tests {
         assert_eq!(blocks[1..], before_blocks_except_first);
     }
 
+    #[test]
+    fn test_prune() {
+        let temp_dir = temp_dir();
+        let block_store = BlockStore::<DummyBlock>::create_or_load(temp_dir.path()).unwrap();
+
+        for i in 0..10u8 {
+            block_store
+                .set(&DummyBlock::V1((
+                    BlockHeight(i as u64),
+                    [i; 32],
+                    vec![DummyTxn::V1([i + 100; 32])],
+                )))
+                .unwrap();
+        }
+
+        assert_eq!(block_store.get_pruned_height().unwrap(), None);
+
+        block_store.prune(BlockHeight(5)).unwrap();
+
+        assert_eq!(block_store.get_pruned_height().unwrap(), Some(BlockHeight(5)));
+        assert_eq!(block_store.get(BlockHeight(4)).unwrap(), None);
+        assert!(block_store.get(BlockHeight(5)).unwrap().is_some());
+        assert_eq!(block_store.get_block_height_by_hash([4; 32]).unwrap(), None);
+        assert_eq!(block_store.get_txn_by_hash([104; 32]).unwrap(), None);
+        assert!(block_store.get_txn_by_hash([105; 32]).unwrap().is_some());
+        assert_eq!(block_store.get_txn_location_by_leaf([104; 32]).unwrap(), None);
+        assert_eq!(
+            block_store
+                .list_non_empty(.., BlockListOrder::LowestToHighest)
+                .into_iterator()
+                .count(),
+            5
+        );
+
+        // Pruning below the pruned height is a no-op
+        block_store.prune(BlockHeight(3)).unwrap();
+        assert_eq!(block_store.get_pruned_height().unwrap(), Some(BlockHeight(5)));
+        assert_eq!(block_store.get_max_height().unwrap(), Some(BlockHeight(9)));
+    }
+
+    #[test]
+    fn test_rejected_txns() {
+        let temp_dir = temp_dir();
+        let block_store = BlockStore::<DummyBlock>::create_or_load(temp_dir.path()).unwrap();
+
+        let get = |hash: u8| {
+            block_store
+                .get_rejected_txn::<DummyTxn>([hash; 32])
+                .unwrap()
+        };
+
+        for i in 0..5u8 {
+            block_store
+                .set_rejected_txn([i; 32], &DummyTxn::V1([i + 100; 32]), 3)
+                .unwrap();
+        }
+
+        assert_eq!(get(0), None);
+        assert_eq!(get(1), None);
+        assert_eq!(get(2), Some(DummyTxn::V1([102; 32])));
+        assert_eq!(get(4), Some(DummyTxn::V1([104; 32])));
+
+        // Rejecting a txn again replaces the old rejection, and its old entry expiring
+        // doesn't delete the new one
+        block_store
+            .set_rejected_txn([2; 32], &DummyTxn::V1([200; 32]), 3)
+            .unwrap();
+        block_store
+            .set_rejected_txn([5; 32], &DummyTxn::V1([105; 32]), 3)
+            .unwrap();
+
+        assert_eq!(get(2), Some(DummyTxn::V1([200; 32])));
+        assert_eq!(get(3), None);
+        assert_eq!(get(4), Some(DummyTxn::V1([104; 32])));
+        assert_eq!(get(5), Some(DummyTxn::V1([105; 32])));
+    }
+
     #[test]
     fn successor() {
         let temp_dir = temp_dir();
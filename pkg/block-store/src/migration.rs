@@ -6,7 +6,7 @@ use crate::{
     Block, BlockStore, Error, StoreList,
 };
 
-pub(crate) const LATEST_VERSION: u32 = 1;
+pub(crate) const LATEST_VERSION: u32 = 2;
 
 impl<B> BlockStore<B>
 where
@@ -19,7 +19,8 @@ where
 
             match version {
                 0 => self.migrate_to_v1()?,
-                1 => break,
+                1 => self.migrate_to_v2()?,
+                2 => break,
                 other => return Err(Error::InvalidVersion(other)),
             }
         }
@@ -57,11 +58,38 @@ where
 
         Ok(())
     }
+
+    /// Backfill the leaf to txn index
+    #[tracing::instrument(skip(self))]
+    fn migrate_to_v2(&self) -> Result<()> {
+        tracing::info!("Migrating block store to version 2");
+
+        for block in self
+            .list_non_empty(.., BlockListOrder::LowestToHighest)
+            .into_iterator()
+        {
+            let (_, block) = block?;
+
+            let mut batch = rocksdb::WriteBatchWithTransaction::<false>::default();
+
+            for (k, v) in Self::leaf_entries(&block) {
+                batch.put(k.serialize(), v);
+            }
+
+            self.db.write(batch)?;
+        }
+
+        self.set_store_version(2)?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::tests::DummyBlock;
+    use crate::keys::Key;
+    use crate::tests::{DummyBlock, DummyTxn};
+    use primitives::block_height::BlockHeight;
 
     use super::*;
     use tempdir::TempDir;
@@ -79,4 +107,33 @@ mod tests {
 
         assert_eq!(block_store.store_version().unwrap(), LATEST_VERSION);
     }
+
+    #[test]
+    fn test_migrate_to_v2_backfills_leaves() {
+        let temp_dir = temp_dir();
+        let block_store = BlockStore::<DummyBlock>::create_or_load(temp_dir.path()).unwrap();
+
+        let block = DummyBlock::V1((
+            BlockHeight(5),
+            [0; 32],
+            vec![DummyTxn::V1([1; 32]), DummyTxn::V1([2; 32])],
+        ));
+        block_store.set(&block).unwrap();
+
+        // Simulate a store written before leaves were indexed
+        block_store
+            .db
+            .delete(Key::LeafToTxn([2; 32]).serialize())
+            .unwrap();
+        block_store.set_store_version(1).unwrap();
+        assert_eq!(block_store.get_txn_location_by_leaf([2; 32]).unwrap(), None);
+
+        block_store.migrate().unwrap();
+
+        assert_eq!(block_store.store_version().unwrap(), LATEST_VERSION);
+        assert_eq!(
+            block_store.get_txn_location_by_leaf([2; 32]).unwrap(),
+            Some((BlockHeight(5), 1))
+        );
+    }
 }
@@ -16,7 +16,7 @@ pub mod wallet;
 pub use across::AcrossWithAuthorizationContract;
 pub use client::Client;
 pub use error::{Error, Result};
-pub use rollup::RollupContract;
+pub use rollup::{RollupContract, ValidatorSet, ValidatorStake};
 pub use usdc::USDCContract;
 
 pub use web3::{
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -27,9 +28,75 @@ use zk_primitives::Element;
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ValidatorSet {
     pub validators: Vec<Address>,
+    /// Voting power of each validator, in the same order as `validators`. The contract doesn't
+    /// store stakes, so they're set with [`RollupContract::with_validator_stakes`], and are 1
+    /// otherwise
+    pub stakes: Vec<U256>,
     pub valid_from: U256,
 }
 
+/// A validator's voting power for a block height
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidatorStake {
+    pub validator: Address,
+    /// Stake in the validator set for the height, 0 if the validator is not in it
+    pub stake_this_epoch: U256,
+    /// Stake in the validator set for the next height, 0 if the validator is not in it
+    pub stake_next_epoch: U256,
+}
+
+impl ValidatorSet {
+    /// The set with each validator's stake taken from `stakes`, 1 for validators that aren't
+    /// in it
+    pub fn with_stakes(self, stakes: &HashMap<Address, U256>) -> Self {
+        let stakes = self
+            .validators
+            .iter()
+            .map(|validator| stakes.get(validator).copied().unwrap_or_else(U256::one))
+            .collect();
+
+        Self { stakes, ..self }
+    }
+
+    /// The stake of `validator`, 0 if it's not in the set
+    pub fn stake(&self, validator: &Address) -> U256 {
+        self.validators
+            .iter()
+            .zip(&self.stakes)
+            .find(|(v, _)| *v == validator)
+            .map_or(U256::zero(), |(_, stake)| *stake)
+    }
+
+    /// The stakes for a block validated by this set, where `next` is the set for the next
+    /// block. The sets differ for the last block before `next` takes over, so that block needs
+    /// approvals from both. Validators in this set come first, in order, then validators only in
+    /// `next`
+    pub fn stakes_with_next(&self, next: &ValidatorSet) -> Vec<ValidatorStake> {
+        let this_epoch = self
+            .validators
+            .iter()
+            .zip(&self.stakes)
+            .map(|(validator, stake)| ValidatorStake {
+                validator: *validator,
+                stake_this_epoch: *stake,
+                stake_next_epoch: next.stake(validator),
+            });
+
+        let next_epoch_only = next
+            .validators
+            .iter()
+            .zip(&next.stakes)
+            .filter(|(validator, _)| !self.validators.contains(validator))
+            .map(|(validator, stake)| ValidatorStake {
+                validator: *validator,
+                stake_this_epoch: U256::zero(),
+                stake_next_epoch: *stake,
+            });
+
+        this_epoch.chain(next_epoch_only).collect()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Burn {
     pub to: H256,
@@ -116,9 +183,11 @@ impl Tokenizable for ValidatorSet {
 
                 let validators = Vec::<Address>::from_token(validators)?;
                 let valid_from = U256::from_token(valid_from)?;
+                let stakes = vec![U256::one(); validators.len()];
 
                 Ok(Self {
                     validators,
+                    stakes,
                     valid_from,
                 })
             }
@@ -146,6 +215,8 @@ pub struct RollupContract {
     pub signer_address: Address,
     pub domain_separator: H256,
     pub validator_sets: Arc<RwLock<Vec<ValidatorSet>>>,
+    /// Stakes of the validators, see [`Self::with_validator_stakes`]
+    validator_stakes: HashMap<Address, U256>,
     address: Address,
     /// The ethereum block height used for all contract calls.
     /// If None, the latest block is used.
@@ -169,6 +240,7 @@ impl RollupContract {
             signer_address,
             domain_separator,
             validator_sets: Arc::new(RwLock::new(Vec::new())),
+            validator_stakes: HashMap::new(),
             address,
             block_height: None,
         }
@@ -224,6 +296,45 @@ impl RollupContract {
         }
     }
 
+    /// Use `validator_stakes` as the voting power of validators, as the contract doesn't store
+    /// stakes. Validators that aren't in it have a stake of 1
+    pub fn with_validator_stakes(self, validator_stakes: HashMap<Address, U256>) -> Self {
+        Self {
+            validator_stakes,
+            ..self
+        }
+    }
+
+    /// The stakes set with [`Self::with_validator_stakes`]
+    pub fn validator_stakes(&self) -> &HashMap<Address, U256> {
+        &self.validator_stakes
+    }
+
+    /// A hash of the stakes set with [`Self::with_validator_stakes`], in address order, so
+    /// validators can check they're using the same stakes
+    pub fn validator_stakes_hash(&self) -> H256 {
+        let mut stakes = self.validator_stakes.iter().collect::<Vec<_>>();
+        stakes.sort();
+
+        let mut hasher = Keccak256::new();
+        for (validator, stake) in stakes {
+            let mut stake_bytes = [0u8; 32];
+            stake.to_big_endian(&mut stake_bytes);
+            hasher.update(validator.as_bytes());
+            hasher.update(stake_bytes);
+        }
+
+        H256::from_slice(&hasher.finalize())
+    }
+
+    /// Whether `address` is in any of the contract's validator sets
+    pub fn is_known_validator(&self, address: &Address) -> bool {
+        self.validator_sets
+            .read()
+            .iter()
+            .any(|set| set.validators.contains(address))
+    }
+
     async fn load_all_validators(&self) -> Result<()> {
         let all_validators = self.get_validator_sets(0).await?;
         *self.validator_sets.write() = all_validators;
@@ -806,6 +917,10 @@ impl RollupContract {
     }
 
     pub fn validators_for_height(&self, height: u64) -> Vec<Address> {
+        self.validator_set_for_height(height).validators
+    }
+
+    pub fn validator_set_for_height(&self, height: u64) -> ValidatorSet {
         self
             .validator_sets
             .read()
@@ -813,8 +928,15 @@ impl RollupContract {
             .filter(|v| height >= v.valid_from.as_u64())
             .last()
             .expect("No valid validator set found. This should not be possible, unless the contract is uninitialized")
-            .validators
             .clone()
+            .with_stakes(&self.validator_stakes)
+    }
+
+    /// The stakes of the validators whose approvals count for the block at `height`, see
+    /// [`ValidatorSet::stakes_with_next`]
+    pub fn validator_stakes_for_height(&self, height: u64) -> Vec<ValidatorStake> {
+        self.validator_set_for_height(height)
+            .stakes_with_next(&self.validator_set_for_height(height + 1))
     }
 
     #[tracing::instrument(err, ret, skip(self))]
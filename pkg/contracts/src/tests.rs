@@ -2,6 +2,7 @@ use crate::util::{convert_element_to_h256, convert_h160_to_element};
 use secp256k1::rand::random;
 use secp256k1::PublicKey;
 use smirk::Element;
+use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -569,3 +570,75 @@ fn empty_root() {
     let hash = expect_test::expect_file!["./empty_merkle_tree_root_hash.txt"];
     hash.assert_eq(format!("{:?}", tree.root_hash().to_base()).as_str());
 }
+
+#[test]
+fn validator_set_stakes() {
+    let [a, b, c] = [1, 2, 3].map(Address::from_low_u64_be);
+
+    // The contract doesn't store stakes, so every validator has a stake of 1
+    let set = ValidatorSet::from_token(Token::Tuple(vec![
+        Token::Array(vec![Token::Address(a), Token::Address(b)]),
+        Token::Uint(U256::from(10)),
+    ]))
+    .unwrap();
+    assert_eq!(set.stakes, vec![U256::one(), U256::one()]);
+    assert_eq!(
+        ValidatorSet::from_token(set.clone().into_token()).unwrap(),
+        set
+    );
+
+    let next = ValidatorSet::from_token(Token::Tuple(vec![
+        Token::Array(vec![Token::Address(b), Token::Address(c)]),
+        Token::Uint(U256::from(20)),
+    ]))
+    .unwrap()
+    .with_stakes(&HashMap::from([(b, U256::from(5)), (c, U256::from(7))]));
+    assert_eq!(next.stakes, vec![U256::from(5), U256::from(7)]);
+
+    // Within a validator set, this and next epoch stakes are the same
+    assert_eq!(
+        next.stakes_with_next(&next),
+        vec![
+            ValidatorStake {
+                validator: b,
+                stake_this_epoch: U256::from(5),
+                stake_next_epoch: U256::from(5),
+            },
+            ValidatorStake {
+                validator: c,
+                stake_this_epoch: U256::from(7),
+                stake_next_epoch: U256::from(7),
+            },
+        ]
+    );
+
+    // At the transition, validators of both sets are included
+    assert_eq!(
+        set.stakes_with_next(&next),
+        vec![
+            ValidatorStake {
+                validator: a,
+                stake_this_epoch: U256::one(),
+                stake_next_epoch: U256::zero(),
+            },
+            ValidatorStake {
+                validator: b,
+                stake_this_epoch: U256::one(),
+                stake_next_epoch: U256::from(5),
+            },
+            ValidatorStake {
+                validator: c,
+                stake_this_epoch: U256::zero(),
+                stake_next_epoch: U256::from(7),
+            },
+        ]
+    );
+
+    // Only the contract's `(validators, validFrom)` tuple is accepted
+    assert!(ValidatorSet::from_token(Token::Tuple(vec![
+        Token::Array(vec![Token::Address(a)]),
+        Token::Uint(U256::from(10)),
+        Token::Array(vec![Token::Uint(U256::from(5))]),
+    ]))
+    .is_err());
+}
@@ -94,6 +94,22 @@ impl TryFrom<Approval> for ApprovalValidated {
     }
 }
 
+impl Approval {
+    /// Check the approval is signed by one of `validators`, which should be the validators for
+    /// the approval's target height
+    pub fn validate(self, validators: &[Address]) -> Result<ApprovalValidated, Error> {
+        let approval = ApprovalValidated::try_from(self)?;
+
+        if !validators.contains(&approval.validator) {
+            return Err(Error::NotAValidator {
+                validator: approval.validator,
+            });
+        }
+
+        Ok(approval)
+    }
+}
+
 impl ApprovalContent {
     pub fn new(
         parent_hash: CryptoHash,
@@ -115,15 +131,22 @@ impl ApprovalContent {
         }
     }
 
-    fn hash(&self) -> CryptoHash {
+    /// The hash signed by validators
+    pub fn hash(&self) -> CryptoHash {
         let mut hasher = Keccak256::new();
         let mut height_bytes = [0u8; 32];
         U256::from(self.target_height).to_big_endian(&mut height_bytes);
         hasher.update(height_bytes);
-        hasher.update(match &self.inner {
-            ApprovalInner::Endorsement(h) => h.inner(),
-            ApprovalInner::Skip(_) => todo!(),
-        });
+        match &self.inner {
+            ApprovalInner::Endorsement(h) => hasher.update(h.inner()),
+            ApprovalInner::Skip(parent_height) => {
+                // Prefixed, so a skip can't have the same hash as an endorsement
+                let mut parent_height_bytes = [0u8; 32];
+                U256::from(*parent_height).to_big_endian(&mut parent_height_bytes);
+                hasher.update(b"skip");
+                hasher.update(parent_height_bytes);
+            }
+        }
         CryptoHash(hasher.finalize().into())
     }
 
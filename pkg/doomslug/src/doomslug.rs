@@ -1,4 +1,4 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::time::{Duration, Instant};
 
 use crate::approval::{
@@ -577,6 +577,30 @@ impl Doomslug {
             && (approved_stake2 > threshold2 || threshold2 == 0)
     }
 
+    /// Whether `approvers` hold more than 2/3 of the stake, i.e. whether a block containing
+    /// their endorsements of its parent makes the parent doomslug final
+    ///
+    /// # Arguments
+    /// * `approvers` - the validators whose endorsements are in the block
+    /// * `stakes`    - all the stakes of all the block producers in the current epoch
+    pub fn has_finality_threshold(
+        approvers: &HashSet<Address>,
+        stakes: &[(ApprovalStake, bool)],
+    ) -> bool {
+        let approvals = stakes
+            .iter()
+            .map(|(stake, _)| approvers.contains(&stake.validator).then(|| Box::new(true)))
+            .collect::<Vec<_>>();
+        let stakes = stakes
+            .iter()
+            .map(|(stake, is_slashed)| {
+                (stake.stake_this_epoch, stake.stake_next_epoch, *is_slashed)
+            })
+            .collect::<Vec<_>>();
+
+        Self::can_approved_block_be_produced(DoomslugThresholdMode::TwoThirds, &approvals, &stakes)
+    }
+
     pub fn get_witness(
         &self,
         prev_hash: &CryptoHash,
@@ -778,3 +802,248 @@ impl Doomslug {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use primitives::peer::PeerIdSigner;
+
+    use super::*;
+    use crate::{block_producer, Error};
+
+    const ENDORSEMENT_DELAY: Duration = Duration::from_millis(100);
+
+    struct TestValidator {
+        signer: PeerIdSigner,
+        doomslug: Doomslug,
+        online: bool,
+    }
+
+    /// An in-process network of validators, which produce blocks in turn once the block
+    /// producer has endorsements from more than 2/3 of the validators
+    struct TestNetwork {
+        validators: Vec<TestValidator>,
+        now: Instant,
+        tip: (CryptoHash, BlockHeight),
+        final_height: BlockHeight,
+        producers: Vec<Address>,
+    }
+
+    impl TestNetwork {
+        fn new(count: usize) -> Self {
+            let now = Instant::now();
+            let genesis = CryptoHash::genesis();
+
+            let validators = (0..count)
+                .map(|_| {
+                    let mut doomslug = Doomslug::new(
+                        0,
+                        ENDORSEMENT_DELAY,
+                        Duration::from_millis(1000),
+                        Duration::from_millis(100),
+                        Duration::from_millis(3000),
+                        DoomslugThresholdMode::TwoThirds,
+                    );
+                    doomslug.on_block(now, genesis, 0, 0);
+
+                    TestValidator {
+                        signer: PeerIdSigner::default(),
+                        doomslug,
+                        online: true,
+                    }
+                })
+                .collect();
+
+            Self {
+                validators,
+                now,
+                tip: (genesis, 0),
+                final_height: 0,
+                producers: vec![],
+            }
+        }
+
+        fn addresses(&self) -> Vec<Address> {
+            self.validators.iter().map(|v| v.signer.address()).collect()
+        }
+
+        fn stakes(&self) -> Vec<(ApprovalStake, bool)> {
+            self.addresses()
+                .into_iter()
+                .map(|validator| {
+                    (
+                        ApprovalStake {
+                            validator,
+                            stake_this_epoch: 1,
+                            stake_next_epoch: 1,
+                        },
+                        false,
+                    )
+                })
+                .collect()
+        }
+
+        fn producer_index(&self, height: BlockHeight) -> usize {
+            let addresses = self.addresses();
+            let producer = block_producer(&addresses, height).unwrap();
+            addresses.iter().position(|a| a == producer).unwrap()
+        }
+
+        /// Advance time by the endorsement delay, deliver the approvals sent by online
+        /// validators to the block producers they target, and produce the next block if its
+        /// producer is ready
+        fn step(&mut self) {
+            self.now += ENDORSEMENT_DELAY;
+            let now = self.now;
+            let addresses = self.addresses();
+            let stakes = self.stakes();
+
+            let mut approvals = vec![];
+            for validator in self.validators.iter_mut().filter(|v| v.online) {
+                for content in validator.doomslug.process_timer(now) {
+                    approvals.push(content.to_approval(&validator.signer));
+                }
+            }
+
+            for approval in approvals {
+                let producer = self.producer_index(approval.content.target_height);
+                let approval = approval.validate(&addresses).unwrap();
+
+                let validator = &mut self.validators[producer];
+                if validator.online {
+                    validator.doomslug.on_approval(now, &approval, &stakes);
+                }
+            }
+
+            let (tip_hash, tip_height) = self.tip;
+            let height = tip_height + 1;
+            let producer = self.producer_index(height);
+            let validator = &mut self.validators[producer];
+            if !validator.online
+                || !validator
+                    .doomslug
+                    .ready_to_produce_block(now, height, true, false)
+            {
+                return;
+            }
+
+            let approvers = validator
+                .doomslug
+                .get_witness(&tip_hash, tip_height, height)
+                .into_keys()
+                .collect::<HashSet<_>>();
+            if Doomslug::has_finality_threshold(&approvers, &stakes) {
+                self.final_height = tip_height;
+            }
+
+            let block_hash = CryptoHash::from_u64(height);
+            for validator in self.validators.iter_mut().filter(|v| v.online) {
+                validator
+                    .doomslug
+                    .on_block(now, block_hash, height, self.final_height);
+            }
+
+            self.tip = (block_hash, height);
+            self.producers.push(addresses[producer].clone());
+        }
+    }
+
+    #[test]
+    fn test_validators_take_turns() {
+        let mut network = TestNetwork::new(4);
+
+        for _ in 0..50 {
+            network.step();
+        }
+
+        let (_, height) = network.tip;
+        assert!(height >= 8, "only produced {height} blocks");
+
+        // Every block's endorsements finalized its parent
+        assert_eq!(network.final_height, height - 1);
+        for validator in &network.validators {
+            assert_eq!(validator.doomslug.get_tip(), network.tip);
+            assert_eq!(validator.doomslug.get_largest_final_height(), height - 1);
+        }
+
+        // Blocks are produced by each validator in turn
+        let addresses = network.addresses();
+        for (i, producer) in network.producers.iter().enumerate() {
+            let height = i as BlockHeight + 1;
+            assert_eq!(Some(producer), block_producer(&addresses, height));
+        }
+    }
+
+    #[test]
+    fn test_no_blocks_without_threshold() {
+        let mut network = TestNetwork::new(4);
+
+        // 2 of 4 validators is not more than 2/3
+        network.validators[2].online = false;
+        network.validators[3].online = false;
+
+        for _ in 0..50 {
+            network.step();
+        }
+
+        assert_eq!(network.tip.1, 0);
+        assert_eq!(network.final_height, 0);
+    }
+
+    #[test]
+    fn test_approval_from_non_validator() {
+        let network = TestNetwork::new(4);
+        let outsider = PeerIdSigner::default();
+
+        let approval =
+            ApprovalContent::new_endorsement(&CryptoHash::genesis(), 1).to_approval(&outsider);
+
+        assert!(matches!(
+            approval.validate(&network.addresses()),
+            Err(Error::NotAValidator { validator }) if validator == outsider.address()
+        ));
+    }
+
+    #[test]
+    fn test_skip_hash_differs_from_endorsement() {
+        let endorsement = ApprovalContent::new_endorsement(&CryptoHash::from_u64(1), 3);
+        let skip = ApprovalContent::new(CryptoHash::from_u64(1), 1, 3);
+
+        assert_eq!(skip.inner, ApprovalInner::Skip(1));
+        assert_ne!(endorsement.hash(), skip.hash());
+    }
+
+    #[test]
+    fn test_finality_threshold_weighted_by_stake() {
+        let [a, b, c, d] = [(); 4].map(|_| PeerIdSigner::default().address());
+        let stake = |validator: &Address, stake_this_epoch, stake_next_epoch| {
+            (
+                ApprovalStake {
+                    validator: validator.clone(),
+                    stake_this_epoch,
+                    stake_next_epoch,
+                },
+                false,
+            )
+        };
+        let is_final = |approvers: &[&Address], stakes: &[(ApprovalStake, bool)]| {
+            let approvers = approvers.iter().map(|&a| a.clone()).collect();
+            Doomslug::has_finality_threshold(&approvers, stakes)
+        };
+
+        // `a` holds more than 2/3 of the stake on its own, `b` and `c` together don't
+        let stakes = [stake(&a, 5, 5), stake(&b, 1, 1), stake(&c, 1, 1)];
+        assert!(is_final(&[&a], &stakes));
+        assert!(!is_final(&[&b, &c], &stakes));
+
+        // At an epoch transition, the block needs more than 2/3 of both epochs' stake
+        let stakes = [
+            stake(&a, 5, 0),
+            stake(&b, 1, 1),
+            stake(&c, 1, 1),
+            stake(&d, 0, 10),
+        ];
+        assert!(!is_final(&[&a], &stakes));
+        assert!(!is_final(&[&d], &stakes));
+        assert!(is_final(&[&a, &d], &stakes));
+    }
+}
@@ -1,5 +1,10 @@
+use primitives::peer::Address;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("invalid signature")]
     InvalidSignature,
+
+    #[error("approval signed by {validator}, which is not a validator for the target height")]
+    NotAValidator { validator: Address },
 }
@@ -1,8 +1,12 @@
 mod approval;
 mod doomslug;
 mod error;
+mod producer;
 mod types;
 
-pub use crate::approval::{Approval, ApprovalContent, ApprovalStake, ApprovalValidated};
+pub use crate::approval::{
+    Approval, ApprovalContent, ApprovalInner, ApprovalStake, ApprovalValidated,
+};
 pub use crate::doomslug::{Doomslug, DoomslugThresholdMode};
 pub use crate::error::Error;
+pub use crate::producer::block_producer;
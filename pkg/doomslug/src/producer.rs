@@ -0,0 +1,16 @@
+use primitives::peer::Address;
+
+use crate::types::BlockHeight;
+
+/// The validator that produces the block at `height`, or `None` if there are no validators.
+///
+/// Validators take turns in the order they appear in the validator set, so every node must see
+/// the set in the same order. This holds for validator sets read from the rollup contract, which
+/// stores them as an array
+pub fn block_producer(validators: &[Address], height: BlockHeight) -> Option<&Address> {
+    if validators.is_empty() {
+        return None;
+    }
+
+    validators.get((height % validators.len() as u64) as usize)
+}
@@ -0,0 +1,349 @@
+//! Export a range of blocks, along with the notes tree at the last block, to a single
+//! checksummed file, and import it into an empty node.
+//!
+//! The archive layout is:
+//!
+//! ```text
+//! magic (8 bytes) | version (u32 be) | borsh(ArchiveContents) | keccak256 of everything before (32 bytes)
+//! ```
+
+use std::path::Path;
+
+use block_store::{Block as _, BlockListOrder, BlockStore, StoreList};
+use borsh::{BorshDeserialize, BorshSerialize};
+use prover::smirk_metadata::SmirkMetadata;
+use sha3::{Digest, Keccak256};
+use tracing::info;
+use wire_message::WireMessage;
+use zk_primitives::Element;
+
+use crate::{
+    config::Config, errors::Result, types::BlockHeight, BlockFormat, Error, PersistentMerkleTree,
+};
+
+const MAGIC: &[u8; 8] = b"PAYYARCH";
+const VERSION: u32 = 1;
+const CHECKSUM_LEN: usize = 32;
+
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+struct ArchiveContents {
+    from: u64,
+    to: u64,
+    /// Each block encoded with [`WireMessage::to_bytes`]
+    blocks: Vec<Vec<u8>>,
+    /// The notes tree at block `to`
+    elements: Vec<(Element, SmirkMetadata)>,
+}
+
+/// The heights of the blocks written to or read from an archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveSummary {
+    pub from: BlockHeight,
+    pub to: BlockHeight,
+    pub blocks: usize,
+    pub elements: usize,
+}
+
+/// Write the blocks `from..=to` and the notes tree at `to` from the node's database to `path`.
+/// The node must not be running.
+pub fn export(
+    config: &Config,
+    from: BlockHeight,
+    to: BlockHeight,
+    path: &Path,
+) -> Result<ArchiveSummary> {
+    let block_store = BlockStore::<BlockFormat>::create_or_load(&config.db_path.join("latest"))?;
+    let notes_tree = PersistentMerkleTree::load(config.smirk_path.join("latest"))?;
+
+    let summary = write_archive(&block_store, &notes_tree, from, to, path)?;
+    info!(?summary, path = ?path, "Exported archive");
+
+    Ok(summary)
+}
+
+/// Read an archive written by [`export`] into the node's database, which must be empty.
+/// The node must not be running.
+pub fn import(config: &Config, path: &Path) -> Result<ArchiveSummary> {
+    let block_store = BlockStore::<BlockFormat>::create_or_load(&config.db_path.join("latest"))?;
+    let mut notes_tree = PersistentMerkleTree::load(config.smirk_path.join("latest"))?;
+
+    let summary = read_archive(&block_store, &mut notes_tree, path)?;
+    info!(?summary, path = ?path, "Imported archive");
+
+    Ok(summary)
+}
+
+fn write_archive(
+    block_store: &BlockStore<BlockFormat>,
+    notes_tree: &PersistentMerkleTree,
+    from: BlockHeight,
+    to: BlockHeight,
+    path: &Path,
+) -> Result<ArchiveSummary> {
+    if from > to {
+        return Err(Error::InvalidArchive {
+            reason: format!("from height {from} is after to height {to}"),
+        });
+    }
+
+    let max_height = block_store.get_max_height()?.unwrap_or(BlockHeight(0));
+    if to > max_height {
+        return Err(Error::BlockNotFound { block: to });
+    }
+
+    let blocks = block_store
+        .list(from..=to, BlockListOrder::LowestToHighest)
+        .into_iterator()
+        .map(|block| Ok(block?.1.to_bytes()?))
+        .collect::<Result<Vec<_>>>()?;
+
+    if blocks.len() as u64 != to.0 - from.0 + 1 {
+        return Err(Error::InvalidArchive {
+            reason: format!("blocks {from} to {to} are not all in the block store"),
+        });
+    }
+
+    // The tree may be ahead of `to`, so drop anything inserted after it
+    let elements = notes_tree
+        .tree()
+        .elements()
+        .filter(|(_, meta)| meta.inserted_in <= to.0)
+        .map(|(element, meta)| (*element, meta.clone()))
+        .collect::<Vec<_>>();
+
+    let contents = ArchiveContents {
+        from: from.0,
+        to: to.0,
+        blocks,
+        elements,
+    };
+    let summary = contents.summary();
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&VERSION.to_be_bytes());
+    contents.serialize(&mut bytes)?;
+    let checksum = Keccak256::digest(&bytes);
+    bytes.extend_from_slice(&checksum);
+
+    std::fs::write(path, bytes)?;
+
+    Ok(summary)
+}
+
+fn read_archive(
+    block_store: &BlockStore<BlockFormat>,
+    notes_tree: &mut PersistentMerkleTree,
+    path: &Path,
+) -> Result<ArchiveSummary> {
+    let bytes = std::fs::read(path)?;
+    let contents = decode(&bytes)?;
+
+    if block_store.get_max_height()?.is_some() || !notes_tree.tree().is_empty() {
+        return Err(Error::InvalidArchive {
+            reason: "the block store and notes tree must be empty to import an archive".to_string(),
+        });
+    }
+
+    let blocks = contents
+        .blocks
+        .iter()
+        .map(|bytes| Ok(BlockFormat::from_bytes(bytes)?))
+        .collect::<Result<Vec<_>>>()?;
+
+    let last_block = match blocks.last() {
+        Some(block) if block.block_height() == BlockHeight(contents.to) => {
+            block.clone().into_block()
+        }
+        _ => {
+            return Err(Error::InvalidArchive {
+                reason: format!("archive does not end with block {}", contents.to),
+            })
+        }
+    };
+
+    // Check the tree before writing anything, so a bad archive leaves the node empty
+    let elements = contents
+        .elements
+        .iter()
+        .map(|(element, _)| *element)
+        .collect::<Vec<_>>();
+    let root_hash = notes_tree.tree().root_hash_with(&elements);
+    if root_hash != last_block.content.state.root_hash {
+        return Err(Error::InvalidBlockRoot {
+            got: root_hash,
+            expected: last_block.content.state.root_hash,
+        });
+    }
+
+    for block in &blocks {
+        block_store.set(block)?;
+    }
+
+    let batch = smirk::Batch::from_entries(contents.elements.iter().cloned())?;
+    notes_tree.insert_batch(batch)?;
+
+    if contents.from > 0 {
+        block_store.prune(BlockHeight(contents.from))?;
+    }
+
+    Ok(contents.summary())
+}
+
+fn decode(bytes: &[u8]) -> Result<ArchiveContents> {
+    let invalid = |reason: &str| Error::InvalidArchive {
+        reason: reason.to_string(),
+    };
+
+    if bytes.len() < MAGIC.len() + 4 + CHECKSUM_LEN || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(invalid("not an archive"));
+    }
+
+    let (body, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+    if Keccak256::digest(body).as_slice() != checksum {
+        return Err(invalid("checksum mismatch"));
+    }
+
+    let (version, mut contents) = body[MAGIC.len()..].split_at(4);
+    #[allow(clippy::unwrap_used)]
+    let version = u32::from_be_bytes(version.try_into().unwrap());
+    if version != VERSION {
+        return Err(Error::InvalidArchive {
+            reason: format!("unsupported archive version {version}"),
+        });
+    }
+
+    let contents = ArchiveContents::deserialize(&mut contents)?;
+    if contents.from > contents.to {
+        return Err(invalid("from height is after to height"));
+    }
+
+    Ok(contents)
+}
+
+impl ArchiveContents {
+    fn summary(&self) -> ArchiveSummary {
+        ArchiveSummary {
+            from: BlockHeight(self.from),
+            to: BlockHeight(self.to),
+            blocks: self.blocks.len(),
+            elements: self.elements.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use tempdir::TempDir;
+
+    struct Db {
+        _dir: TempDir,
+        block_store: BlockStore<BlockFormat>,
+        notes_tree: PersistentMerkleTree,
+    }
+
+    impl Db {
+        fn new() -> Self {
+            let dir = TempDir::new("archive").unwrap();
+            let block_store = BlockStore::create_or_load(&dir.path().join("db")).unwrap();
+            let notes_tree = PersistentMerkleTree::new(dir.path().join("smirk")).unwrap();
+
+            Self {
+                _dir: dir,
+                block_store,
+                notes_tree,
+            }
+        }
+    }
+
+    /// Blocks 0..=3, where block `n` inserts element `n` into the tree
+    fn source_db() -> Db {
+        let mut db = Db::new();
+
+        for height in 0..=3 {
+            db.notes_tree
+                .insert(Element::new(height + 1), SmirkMetadata::inserted_in(height))
+                .unwrap();
+
+            let mut block = Block::default();
+            block.content.header.height = BlockHeight(height);
+            block.content.state.root_hash = db.notes_tree.tree().root_hash();
+
+            db.block_store.set(&BlockFormat::V1(block)).unwrap();
+        }
+
+        db
+    }
+
+    #[test]
+    fn export_and_import() {
+        let source = source_db();
+        let path = source._dir.path().join("out.payy");
+
+        let summary = write_archive(
+            &source.block_store,
+            &source.notes_tree,
+            BlockHeight(1),
+            BlockHeight(2),
+            &path,
+        )
+        .unwrap();
+        assert_eq!(summary.blocks, 2);
+        assert_eq!(summary.elements, 3);
+
+        let mut target = Db::new();
+        read_archive(&target.block_store, &mut target.notes_tree, &path).unwrap();
+
+        assert_eq!(
+            target.block_store.get_max_height().unwrap(),
+            Some(BlockHeight(2))
+        );
+        assert_eq!(
+            target.block_store.get_pruned_height().unwrap(),
+            Some(BlockHeight(1))
+        );
+        assert!(target.block_store.get(BlockHeight(0)).unwrap().is_none());
+        assert!(target.block_store.get(BlockHeight(1)).unwrap().is_some());
+        assert_eq!(
+            target.notes_tree.tree().root_hash(),
+            source
+                .block_store
+                .get(BlockHeight(2))
+                .unwrap()
+                .unwrap()
+                .into_block()
+                .content
+                .state
+                .root_hash
+        );
+
+        // Importing again into a non-empty node fails
+        assert!(read_archive(&target.block_store, &mut target.notes_tree, &path).is_err());
+    }
+
+    #[test]
+    fn import_rejects_corrupt_archive() {
+        let source = source_db();
+        let path = source._dir.path().join("out.payy");
+
+        write_archive(
+            &source.block_store,
+            &source.notes_tree,
+            BlockHeight(0),
+            BlockHeight(3),
+            &path,
+        )
+        .unwrap();
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 1;
+        std::fs::write(&path, bytes).unwrap();
+
+        let mut target = Db::new();
+        let err = read_archive(&target.block_store, &mut target.notes_tree, &path).unwrap_err();
+        assert!(matches!(err, Error::InvalidArchive { .. }), "{err:?}");
+        assert_eq!(target.block_store.get_max_height().unwrap(), None);
+    }
+}
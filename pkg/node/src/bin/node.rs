@@ -5,10 +5,15 @@ use clap::Parser;
 use eyre::Result;
 use futures::Future;
 use node::{
-    config::{cli::CliArgs, Config},
+    config::{
+        cli::{CliArgs, Command},
+        watch::watch_ip_filter,
+        Config,
+    },
     create_rpc_server,
 };
 use node::{Mode, Node, TxnStats};
+use primitives::block_height::BlockHeight;
 use rpc::tracing::setup_tracing;
 
 #[tokio::main]
@@ -18,6 +23,8 @@ async fn main() -> Result<()> {
     let args = CliArgs::parse();
 
     let config = Config::from_env(args.clone()).unwrap();
+    // Used to reload the config when the file changes
+    let reload_args = args.clone();
 
     let _guard = setup_tracing(
         &[
@@ -36,6 +43,18 @@ async fn main() -> Result<()> {
         config.env_name.clone(),
     )?;
 
+    match args.command {
+        Some(Command::Export { from, to, path }) => {
+            node::archive::export(&config, BlockHeight(from), BlockHeight(to), &path)?;
+            return Ok(());
+        }
+        Some(Command::Import { path }) => {
+            node::archive::import(&config, &path)?;
+            return Ok(());
+        }
+        None => {}
+    }
+
     // Listen address of the server
     let rpc_laddr = config.rpc_laddr.clone();
 
@@ -47,9 +66,15 @@ async fn main() -> Result<()> {
             .unwrap();
     let contracts_client =
         contracts::Client::new(&config.eth_rpc_url, config.minimum_gas_price_gwei);
+    let validator_stakes = config
+        .validator_stakes
+        .iter()
+        .map(|(validator, stake)| (*validator, contracts::U256::from(*stake)))
+        .collect();
     let contract =
         contracts::RollupContract::load(contracts_client, &config.rollup_contract_addr, secret_key)
-            .await?;
+            .await?
+            .with_validator_stakes(validator_stakes);
 
     // Services
     let node = Node::new(peer_signer, contract.clone(), config.clone()).unwrap();
@@ -87,6 +112,9 @@ async fn main() -> Result<()> {
         res = txn_stats.worker() => {
             tracing::info!("txn stats worker shutdown: {:?}", res);
         }
+        res = watch_ip_filter(reload_args, Arc::clone(&node.shared)) => {
+            tracing::info!("config watcher shutdown: {:?}", res);
+        }
     }
 
     Ok(())
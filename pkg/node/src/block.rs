@@ -1,13 +1,18 @@
 use block_store::BlockStore;
 use borsh::{BorshDeserialize, BorshSerialize};
+use doomslug::ApprovalContent;
 use ethereum_types::U256;
-use primitives::{hash::CryptoHash, peer::PeerIdSigner};
+use primitives::{
+    hash::CryptoHash,
+    peer::{Address, PeerIdSigner},
+};
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
 use std::{collections::HashMap, fmt::Debug};
 use tracing::error;
 use zk_primitives::Element;
 
+use crate::cache::VerifiedProofCache;
 use crate::types::BlockHeight;
 use crate::{
     utxo::{validate_txn, UtxoProof},
@@ -43,6 +48,21 @@ pub struct BlockHeader {
     pub approvals: Vec<Signature>,
 }
 
+impl BlockHeader {
+    /// The signers of the header's approvals, `None` for approvals with an invalid signature.
+    /// If the previous block is at `parent_height` = `height - 1` the approvals endorse it,
+    /// otherwise they skip the heights in between
+    pub fn approvers(&self, parent_height: BlockHeight) -> Vec<Option<Address>> {
+        let approval =
+            ApprovalContent::new(self.last_block_hash, parent_height.0, self.height.0).hash();
+
+        self.approvals
+            .iter()
+            .map(|signature| signature.verify(&approval))
+            .collect()
+    }
+}
+
 #[derive(
     Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize,
 )]
@@ -139,6 +159,7 @@ impl BlockContent {
         mode: Mode,
         block_store: &BlockStore<BlockFormat>,
         notes_tree: &PersistentMerkleTree,
+        verified_proofs: &VerifiedProofCache,
     ) -> Result<(), Error> {
         let mut txn_leaves = HashMap::new();
 
@@ -170,6 +191,7 @@ impl BlockContent {
                 self.header.height,
                 block_store,
                 notes_tree,
+                verified_proofs,
             );
 
             if let Err(err) = result {
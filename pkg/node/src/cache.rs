@@ -1,8 +1,9 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 
+use parking_lot::Mutex;
 use primitives::hash::CryptoHash;
 
-use crate::{block::Block, types::BlockHeight};
+use crate::{block::Block, types::BlockHeight, utxo::UtxoProof};
 
 /// BlockCache for last commit and all other uncomitted blocks. Stores a given maximum
 //  of cached blocks, so if we receive blocks out of order we don't need to do an extra sync.
@@ -125,47 +126,119 @@ impl BlockCache {
     pub fn is_out_of_sync(&self) -> bool {
         // We may have removed later proposals from the cache (if we are
         // far behind the network) to make space for earlier ones
-        if self.max_height
-            > *self
-                .block_hash_heights
-                .iter()
-                .last()
-                .map(|(key, _)| key)
-                .unwrap()
-        {
+        let Some((&last_height, _)) = self.block_hash_heights.iter().last() else {
+            return true;
+        };
+        if self.max_height > last_height {
             return true;
         }
 
-        let range = (self.height.0 + 1)..=self.max_height.0;
+        if last_height == self.height {
+            return false;
+        }
 
-        // Start at the next block after the last confirmed height.
-        // Iterate till max height.
-        for height in range.map(BlockHeight) {
-            // If the block hash does not exist for this height, return true.
-            if !self.block_hash_heights.contains_key(&height) {
-                return true;
+        // Heights can be skipped, so instead of looking for missing heights we check that the
+        // last block links back to the confirmed block
+        self.next_on_chain().is_none()
+    }
+
+    /// The block after the confirmed block, on the chain from the confirmed block to the last
+    /// block in the cache. Follows each block's parent hash, as the chain skips the heights of
+    /// leaders that didn't produce a block
+    fn next_on_chain(&self) -> Option<&Block> {
+        let confirmed_hash = self.block_hash_heights.get(&self.height)?;
+        let (_, last_hash) = self.block_hash_heights.iter().last()?;
+
+        let mut block = self.blocks.get(last_hash)?;
+        while block.content.header.height > self.height {
+            if block.content.header.last_block_hash == *confirmed_hash {
+                return Some(block);
             }
+
+            block = self.blocks.get(&block.content.header.last_block_hash)?;
         }
 
-        // If there are no missing blocks, return false.
-        false
+        None
     }
 
     // If we have a valid chain + 2 then we can commit the latest uncommited block
     pub fn get_next_commit_block(&mut self) -> Option<Block> {
-        let next_height = self.height + BlockHeight(1);
+        if let Some(block) = self.next_on_chain() {
+            return Some(block.clone());
+        }
+
+        // We're missing some of the chain to the last block, but we can still commit the blocks
+        // we have that build on the confirmed block
+        let confirmed_hash = self.block_hash_heights.get(&self.height)?;
+        self.block_hash_heights
+            .range(self.height.next()..)
+            .filter_map(|(_, hash)| self.blocks.get(hash))
+            .find(|block| block.content.header.last_block_hash == *confirmed_hash)
+            .cloned()
+    }
+}
+
+/// Txns whose proofs have already been verified, so a txn we see again (e.g. in a block, after
+/// it was submitted to us or gossiped to us) doesn't have its proof verified again. Stores a
+/// given maximum of txns, evicting the oldest first.
+#[derive(Debug)]
+pub struct VerifiedProofCache {
+    inner: Mutex<VerifiedProofCacheInner>,
+    max_cache_size: usize,
+}
+
+#[derive(Debug, Default)]
+struct VerifiedProofCacheInner {
+    /// Txn hash to the hash of its proof. The txn hash doesn't include the proof, so we need
+    /// to check the proof is the one that was verified
+    proofs: HashMap<CryptoHash, CryptoHash>,
+    /// Txn hashes, oldest first
+    order: VecDeque<CryptoHash>,
+}
+
+impl VerifiedProofCache {
+    pub fn new(max_cache_size: usize) -> Self {
+        VerifiedProofCache {
+            inner: Mutex::new(VerifiedProofCacheInner::default()),
+            max_cache_size,
+        }
+    }
+
+    /// Whether the txn's proof has already been verified
+    pub fn contains(&self, utxo: &UtxoProof) -> bool {
+        let proof_hash = proof_hash(utxo);
+
+        self.inner.lock().proofs.get(&utxo.hash()) == Some(&proof_hash)
+    }
+
+    /// Record that the txn's proof is valid
+    pub fn insert(&self, utxo: &UtxoProof) {
+        if self.max_cache_size == 0 {
+            return;
+        }
 
-        // if we have the next block, confirm it
-        match self.get_by_height(next_height) {
-            Some(block) => {
-                let b = block.clone();
-                Some(b)
+        let txn_hash = utxo.hash();
+        let proof_hash = proof_hash(utxo);
+
+        let mut inner = self.inner.lock();
+        if inner.proofs.insert(txn_hash, proof_hash).is_some() {
+            // Already in the cache
+            return;
+        }
+
+        inner.order.push_back(txn_hash);
+        if inner.order.len() > self.max_cache_size {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.proofs.remove(&oldest);
             }
-            None => None,
         }
     }
 }
 
+fn proof_hash(utxo: &UtxoProof) -> CryptoHash {
+    CryptoHash::from_vec_hash(utxo.proof.clone())
+}
+
 #[cfg(test)]
 mod tests {
     use primitives::sig::Signature;
@@ -175,14 +248,24 @@ mod tests {
 
     use super::*;
 
+    /// Block `height` of a chain without skipped heights
     fn block(height: u64) -> Block {
+        let parent_hash = match height {
+            0 => CryptoHash::default(),
+            _ => block(height - 1).hash(),
+        };
+
+        block_on(height, parent_hash)
+    }
+
+    fn block_on(height: u64, parent_hash: CryptoHash) -> Block {
         Block {
             content: BlockContent {
                 header: BlockHeader {
                     height: BlockHeight(height),
                     epoch_id: 0,
-                    last_block_hash: CryptoHash::from_u64(height.saturating_sub(1)),
-                    last_final_block_hash: CryptoHash::from_u64(height.saturating_sub(1)),
+                    last_block_hash: parent_hash,
+                    last_final_block_hash: parent_hash,
                     approvals: vec![],
                 },
                 state: BlockState {
@@ -194,6 +277,40 @@ mod tests {
         }
     }
 
+    fn utxo(n: u64, proof: Vec<u8>) -> UtxoProof {
+        UtxoProof::new(
+            Element::ZERO,
+            Element::ZERO,
+            Element::ZERO,
+            [Element::from(n), Element::ZERO],
+            [Element::ZERO, Element::ZERO],
+            proof,
+        )
+    }
+
+    #[test]
+    fn test_verified_proof_cache() {
+        let cache = VerifiedProofCache::new(2);
+
+        cache.insert(&utxo(1, vec![1]));
+        assert!(cache.contains(&utxo(1, vec![1])));
+
+        // Same txn hash, but not the proof that was verified
+        assert!(!cache.contains(&utxo(1, vec![2])));
+
+        // Inserting again doesn't take up any more space
+        cache.insert(&utxo(1, vec![1]));
+        cache.insert(&utxo(2, vec![2]));
+        assert!(cache.contains(&utxo(1, vec![1])));
+        assert!(cache.contains(&utxo(2, vec![2])));
+
+        // Oldest is evicted first
+        cache.insert(&utxo(3, vec![3]));
+        assert!(!cache.contains(&utxo(1, vec![1])));
+        assert!(cache.contains(&utxo(2, vec![2])));
+        assert!(cache.contains(&utxo(3, vec![3])));
+    }
+
     #[test]
     fn test_maximum_cache() {
         let mut block_cache = BlockCache::new(block(0), 10);
@@ -305,4 +422,29 @@ mod tests {
         // The chain is now out of sync, we should not be able to commit any block
         // assert!(block_cache.get_next_commit_block().is_none());
     }
+
+    #[test]
+    fn test_skipped_heights() {
+        let mut block_cache = BlockCache::new(block(0), 10);
+        block_cache.insert(block(1));
+
+        // The leader for height 2 didn't produce a block, so block 3 builds on block 1
+        let block_3 = block_on(3, block(1).hash());
+        block_cache.insert(block_3.clone());
+        assert!(!block_cache.is_out_of_sync());
+
+        let next_commit_block = block_cache.get_next_commit_block().unwrap();
+        assert_eq!(next_commit_block, block(1));
+        block_cache.confirm(1.into());
+        assert_eq!(block_cache.get_next_commit_block(), Some(block_3.clone()));
+
+        // A late block for the skipped height isn't on the chain to the last block
+        block_cache.insert(block(2));
+        assert!(!block_cache.is_out_of_sync());
+        assert_eq!(block_cache.get_next_commit_block(), Some(block_3));
+
+        // A block whose parent we don't have
+        block_cache.insert(block_on(5, CryptoHash::from_u64(4)));
+        assert!(block_cache.is_out_of_sync());
+    }
 }
@@ -1,4 +1,4 @@
-use crate::Mode;
+use crate::{config::HistoryMode, Mode};
 use clap::Parser;
 use libp2p::multiaddr::Multiaddr;
 use primitives::peer::PeerIdSigner;
@@ -65,4 +65,34 @@ pub struct CliArgs {
     /// Sync chunk size
     #[arg(long, env = "POLY_SYNC_CHUNK_SIZE")]
     pub sync_chunk_size: Option<u64>,
+
+    /// Whether to keep every block (full), or only recent blocks (pruned)
+    #[arg(value_enum, long, env = "POLY_HISTORY_MODE")]
+    pub history_mode: Option<HistoryMode>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, clap::Subcommand, Serialize, Deserialize)]
+pub enum Command {
+    /// Export blocks and the notes tree to an archive, the node must not be running
+    Export {
+        /// First block to export
+        #[arg(long)]
+        from: u64,
+
+        /// Last block to export, the notes tree is exported as of this block
+        #[arg(long)]
+        to: u64,
+
+        /// Archive path
+        path: PathBuf,
+    },
+
+    /// Import an archive created by `export` into an empty node, the node must not be running
+    Import {
+        /// Archive path
+        path: PathBuf,
+    },
 }
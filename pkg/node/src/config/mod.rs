@@ -1,19 +1,31 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 
 use self::cli::CliArgs;
-use crate::Mode;
-use color_eyre::Result;
+use crate::{constants::RECENT_ROOT_COUNT, MempoolOrdering, Mode};
+use color_eyre::{eyre::bail, Result};
 use dirs::home_dir;
 use figment::{
     providers::{Env, Format, Toml},
     Figment,
 };
 use primitives::peer::PeerIdSigner;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::io::Read;
 use std::{fs::File, str::FromStr};
 
 pub mod cli;
+pub mod watch;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryMode {
+    /// Keep every block
+    #[default]
+    Full,
+
+    /// Keep only the most recent blocks, the notes tree is always kept in full
+    Pruned,
+}
 
 // TODO: should we use kebab-case? Currently _ is used to split into
 // multiple level dictionaries
@@ -29,6 +41,19 @@ pub struct Config {
     /// Maximum number of txns to include in a block
     pub block_txns_count: usize,
 
+    /// Maximum number of pending txns held in the mempool
+    pub mempool_max_size: usize,
+
+    /// Order in which pending txns are included in blocks
+    pub mempool_ordering: MempoolOrdering,
+
+    /// Number of rejected txns to keep the rejection reason for
+    pub rejected_txn_retention: u64,
+
+    /// Number of txns to remember as having a valid proof, so their proofs aren't verified
+    /// again when they are seen in a block
+    pub verified_proof_cache_size: usize,
+
     /// Minimum block duration in seconds
     pub min_block_duration: usize,
 
@@ -40,6 +65,16 @@ pub struct Config {
 
     pub mode: Mode,
 
+    /// Whether to keep every block, or only recent blocks
+    pub history_mode: HistoryMode,
+
+    /// Number of recent blocks to keep when `history_mode` is `pruned`
+    pub pruned_history_blocks: u64,
+
+    /// Write a checkpoint of the notes tree every this many blocks, used to speed up restarts.
+    /// Set to 0 to disable checkpoints
+    pub smirk_checkpoint_interval: u64,
+
     /// Private key of validator
     pub secret_key: PeerIdSigner,
 
@@ -59,6 +94,14 @@ pub struct Config {
 
     pub rollup_contract_addr: String,
 
+    /// Voting power of validators by address, as the rollup contract doesn't store stakes.
+    /// Validators that aren't listed have a stake of 1. Every validator must use the same stakes
+    pub validator_stakes: HashMap<contracts::Address, u64>,
+
+    /// If set, the node refuses to start unless the hash of `validator_stakes` (logged at
+    /// startup and returned by `/status`) matches it, so validators can't silently disagree
+    pub validator_stakes_hash: Option<contracts::H256>,
+
     /// If the last commit is older than this, health check will fail
     pub health_check_commit_interval_sec: u64,
 
@@ -153,6 +196,20 @@ impl Config {
             config.sync_chunk_size = sync_chunk_size;
         }
 
+        if let Some(history_mode) = args.history_mode {
+            config.history_mode = history_mode;
+        }
+
+        if config.history_mode == HistoryMode::Pruned {
+            if config.mode.is_prover() {
+                bail!("history-mode pruned is not supported by provers, they need every block that has not been rolled up");
+            }
+
+            if config.pruned_history_blocks < RECENT_ROOT_COUNT {
+                bail!("pruned-history-blocks must be at least {RECENT_ROOT_COUNT}, to validate txns against recent roots");
+            }
+        }
+
         Ok(config)
     }
 }
@@ -0,0 +1,88 @@
+//! Reload the parts of the config that can change while the node is running
+
+use std::{path::Path, sync::Arc};
+
+use color_eyre::Result;
+use notify::{RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use super::{cli::CliArgs, Config};
+use crate::NodeShared;
+
+/// Reload the config whenever the file at `args.config_path` changes, and apply the new IP
+/// allow and deny lists to the network
+pub async fn watch_ip_filter(args: CliArgs, node: Arc<NodeShared>) -> Result<()> {
+    let path = args.config_path.clone();
+
+    // Watch the directory rather than the file, as editors often replace the file instead of
+    // writing to it, and the file may not exist yet
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    // Failing to watch the file only stops the IP filter being reloaded, so it shouldn't stop
+    // the node
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        tx.send(res).ok();
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(err) => {
+            warn!(
+                ?err,
+                ?path,
+                "Failed to create config file watcher, the IP filter won't be reloaded"
+            );
+            return futures::future::pending().await;
+        }
+    };
+    if let Err(err) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+        warn!(
+            ?err,
+            ?path,
+            "Failed to watch config file, the IP filter won't be reloaded"
+        );
+        return futures::future::pending().await;
+    }
+
+    info!(?path, "Watching config file for IP filter changes");
+
+    while let Some(res) = rx.recv().await {
+        let event = match res {
+            Ok(event) => event,
+            Err(err) => {
+                warn!(?err, "Config file watcher error");
+                continue;
+            }
+        };
+
+        let changed = (event.kind.is_create() || event.kind.is_modify())
+            && event
+                .paths
+                .iter()
+                .any(|p| p.file_name() == path.file_name());
+        if !changed {
+            continue;
+        }
+
+        let config = match Config::from_env(args.clone()) {
+            Ok(config) => config,
+            Err(err) => {
+                warn!(
+                    ?err,
+                    "Failed to reload config, keeping the current IP filter"
+                );
+                continue;
+            }
+        };
+
+        if let Err(err) = node.set_ip_filter(config.p2p.whitelisted_ips, config.p2p.denied_ips) {
+            warn!(?err, "Failed to apply the reloaded IP filter");
+        }
+    }
+
+    Ok(())
+}
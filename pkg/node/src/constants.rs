@@ -12,3 +12,8 @@ pub const MAX_BLOCK_WAIT_DELAY: u64 = 6_000;
 /// Depth of merkle tree
 pub const MERKLE_TREE_DEPTH: usize = 161;
 
+/// Upper bound on the size of a serialized txn, most of which is its proof
+pub const MAX_TXN_SIZE: usize = 64 * 1024;
+
+/// Upper bound on the size of a serialized block, excluding its txns
+pub const MAX_BLOCK_OVERHEAD_SIZE: usize = 64 * 1024;
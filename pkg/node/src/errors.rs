@@ -1,7 +1,8 @@
 use std::num::ParseIntError;
+use std::path::PathBuf;
 
 use libp2p::PeerId;
-use primitives::{block_height::BlockHeight, hash::CryptoHash};
+use primitives::{block_height::BlockHeight, hash::CryptoHash, peer::Address};
 use tracing::error;
 use zk_primitives::Element;
 
@@ -9,7 +10,8 @@ use crate::sync;
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, thiserror::Error, strum_macros::IntoStaticStr)]
+#[strum(serialize_all = "kebab-case")]
 pub enum Error {
     #[error("invalid snapshot chunk, peer mismatch - accepted {accepted}, got {got}")]
     SnapshotChunkPeerMismatch {
@@ -50,9 +52,19 @@ pub enum Error {
         txn_hash: CryptoHash,
     },
 
+    #[error("block height {height} is not recent enough, expected {min_height} to {max_height}")]
+    HeightNotRecentEnough {
+        height: BlockHeight,
+        min_height: BlockHeight,
+        max_height: BlockHeight,
+    },
+
     #[error("element is not in the tree")]
     ElementNotInTree { element: Element },
 
+    #[error("element is in the tree")]
+    ElementInTree { element: Element },
+
     #[error("element is not in any transaction of block {block_height}")]
     ElementNotInTxn {
         element: Element,
@@ -86,6 +98,12 @@ pub enum Error {
     #[error("invalid block root, got: {got}, expected: {expected}")]
     InvalidBlockRoot { got: Element, expected: Element },
 
+    #[error("leaf 0x{leaf:x} is already used by a pending transaction")]
+    LeafAlreadyInMempool { leaf: Element },
+
+    #[error("mempool is full")]
+    MempoolFull,
+
     #[error("failed to find transaction {txn}")]
     TxnNotFound { txn: CryptoHash },
 
@@ -103,12 +121,52 @@ pub enum Error {
         source: rustc_hex::FromHexError,
     },
 
+    #[error("invalid archive: {reason}")]
+    InvalidArchive { reason: String },
+
+    #[error("wire message error: {0}")]
+    WireMessage(#[from] wire_message::Error),
+
     #[error("failed to get eth block number")]
     FailedToGetEthBlockNumber(#[source] web3::Error),
 
     #[error("Invalid accept")]
     DoomslugError(#[from] doomslug::Error),
 
+    #[error("approval for block {target_height} does not endorse block {expected}")]
+    ApprovalNotForTip {
+        target_height: BlockHeight,
+        expected: CryptoHash,
+    },
+
+    #[error("block {height} has an approval that is not an endorsement by a validator")]
+    InvalidBlockApproval { height: BlockHeight },
+
+    #[error("approval by {validator} was sent by peer {peer}, which is bound to {bound}")]
+    ApprovalFromWrongPeer {
+        peer: Box<PeerId>,
+        validator: Address,
+        bound: Address,
+    },
+
+    #[error("validator-stakes lists {0:?}, which isn't a validator in the rollup contract")]
+    UnknownValidatorStake(contracts::Address),
+
+    #[error("validator-stakes gives validator {0:?} a stake of 0")]
+    ZeroValidatorStake(contracts::Address),
+
+    #[error("validator stakes hash {got:?} doesn't match validator-stakes-hash {expected:?}")]
+    ValidatorStakesHashMismatch {
+        expected: contracts::H256,
+        got: contracts::H256,
+    },
+
+    #[error("invalid peer binding from peer {0}")]
+    InvalidPeerBinding(Box<PeerId>),
+
+    #[error("invalid p2p key at {0:?}")]
+    InvalidP2pKey(PathBuf),
+
     #[error("sync error: {0}")]
     Sync(#[from] sync::Error),
 
@@ -2,12 +2,14 @@
 #![feature(once_cell)] // this feature is fine beause it's since been stabilized
 #![feature(bound_map)] // this feature is fine beause it's since been stabilized
 
+pub mod archive;
 mod block;
 mod cache;
 pub mod config;
 mod constants;
 mod errors;
 mod mempool;
+mod metrics;
 mod network;
 mod network_handler;
 mod node;
@@ -20,6 +22,7 @@ mod utxo;
 
 pub use crate::block::Block;
 pub use crate::errors::*;
+pub use crate::mempool::MempoolOrdering;
 pub use crate::node::*;
 pub use crate::rpc::routes::{configure_routes, State};
 pub use crate::rpc::server::create_rpc_server;
@@ -1,34 +1,88 @@
 use parking_lot::Mutex;
-use std::collections::{HashMap, HashSet, VecDeque};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::Hash;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::vec;
 use tokio::sync::oneshot;
+use zk_primitives::Element;
+
+/// The order in which pending txns are leased from the mempool
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum MempoolOrdering {
+    /// Txns are leased in the order they were added, txns released from a lease are retried first
+    #[default]
+    Fifo,
+
+    /// Mint and burn txns are leased before all other txns, otherwise the same as `Fifo`
+    MintBurnFirst,
+
+    /// Txns are always leased oldest first, including txns released from a lease
+    Age,
+}
+
+/// A txn that can be prioritised by [`MempoolOrdering::MintBurnFirst`]
+pub trait MintOrBurn {
+    fn is_mint_or_burn(&self) -> bool;
+}
+
+/// Position of a txn in the pool, the pool is leased from lowest to highest position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct PoolPosition {
+    /// Priority class, lower classes are always leased first
+    class: u8,
+    seq: i64,
+}
 
 struct MempoolTxn<Txn, Change, ChanOkVal> {
     txn: Txn,
     sender: Option<oneshot::Sender<Result<ChanOkVal, crate::Error>>>,
     changes: Vec<Change>,
+    added_at: Instant,
+    /// Sequence number assigned when the txn was added
+    seq: i64,
+    /// Position in the pool, `None` if the txn is leased
+    position: Option<PoolPosition>,
+}
+
+/// A pending txn, as returned by [`Mempool::list`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MempoolEntry<Key> {
+    pub key: Key,
+    /// Time since the txn was added to the mempool
+    pub age: Duration,
+    /// Whether the txn is currently leased for a block proposal
+    pub leased: bool,
 }
 
 #[derive(Clone)]
 pub struct Mempool<Key, Txn, Lease, Change, ChanOkVal> {
     #[allow(clippy::type_complexity)]
     state: Arc<Mutex<MempoolState<Key, Txn, Lease, Change, ChanOkVal>>>,
+    /// Maximum number of txns (pooled and leased) held by the mempool
+    max_size: usize,
+    ordering: MempoolOrdering,
 }
 
 pub struct MempoolState<Key, Txn, Lease, Change, ChanOkVal> {
     txns: HashMap<Key, MempoolTxn<Txn, Change, ChanOkVal>>,
-    pool: VecDeque<Key>,
+    /// Txns available to be leased, indexed by their position
+    pool: BTreeMap<PoolPosition, Key>,
     leased: HashMap<Lease, HashSet<Key>>,
+    /// The txn (pooled or leased) making each change, used to reject conflicting txns
+    changes: HashMap<Change, Key>,
+    /// Next sequence number for txns added to the back of the pool
+    next_seq: i64,
+    /// Next sequence number for txns returned to the front of the pool (counts down)
+    front_seq: i64,
 }
 
 // Manual default impls to avoid unnecessary trait bounds
 impl<Key, Txn, Lease, Change, ChanOkVal> Default for Mempool<Key, Txn, Lease, Change, ChanOkVal> {
     fn default() -> Self {
-        Self {
-            state: Arc::default(),
-        }
+        Self::new(usize::MAX, MempoolOrdering::default())
     }
 }
 
@@ -38,58 +92,218 @@ impl<Key, Txn, Lease, Change, ChanOkVal> Default
     fn default() -> Self {
         Self {
             txns: HashMap::default(),
-            pool: VecDeque::default(),
+            pool: BTreeMap::default(),
             leased: HashMap::default(),
+            changes: HashMap::default(),
+            next_seq: 0,
+            front_seq: -1,
+        }
+    }
+}
+
+impl<Key, Txn, Lease, Change, ChanOkVal> Mempool<Key, Txn, Lease, Change, ChanOkVal> {
+    pub fn new(max_size: usize, ordering: MempoolOrdering) -> Self {
+        Self {
+            state: Arc::default(),
+            max_size,
+            ordering,
         }
     }
 }
 
+impl<K, V, L, C, CV> MempoolState<K, V, L, C, CV>
+where
+    K: Eq + Hash + Clone,
+{
+    /// Put a txn (back) into the pool, `front` is used for txns released from a lease
+    fn push(&mut self, key: K, ordering: MempoolOrdering, is_mint_or_burn: bool, front: bool) {
+        let Some(txn) = self.txns.get(&key) else {
+            return;
+        };
+
+        if txn.position.is_some() {
+            return;
+        }
+
+        let seq = txn.seq;
+        let position = self.position_for(seq, ordering, is_mint_or_burn, front);
+
+        #[allow(clippy::unwrap_used)]
+        let txn = self.txns.get_mut(&key).unwrap();
+        txn.position = Some(position);
+        self.pool.insert(position, key);
+    }
+
+    fn position_for(
+        &mut self,
+        seq: i64,
+        ordering: MempoolOrdering,
+        is_mint_or_burn: bool,
+        front: bool,
+    ) -> PoolPosition {
+        let class = match ordering {
+            MempoolOrdering::MintBurnFirst if is_mint_or_burn => 0,
+            _ => 1,
+        };
+
+        let seq = match ordering {
+            MempoolOrdering::Age => seq,
+            MempoolOrdering::Fifo | MempoolOrdering::MintBurnFirst if front => {
+                let seq = self.front_seq;
+                self.front_seq -= 1;
+                seq
+            }
+            MempoolOrdering::Fifo | MempoolOrdering::MintBurnFirst => seq,
+        };
+
+        PoolPosition { class, seq }
+    }
+
+    /// Remove a txn from the mempool, including from the pool and the changes index
+    fn remove(&mut self, key: &K) -> Option<MempoolTxn<V, C, CV>>
+    where
+        C: Eq + Hash,
+    {
+        self.take_from_pool(key);
+
+        let txn = self.txns.remove(key)?;
+        for change in &txn.changes {
+            self.changes.remove(change);
+        }
+
+        Some(txn)
+    }
+
+    /// Remove a txn from the pool (but not from the mempool), returning true if it was pooled
+    fn take_from_pool(&mut self, key: &K) -> bool {
+        let Some(position) = self.txns.get_mut(key).and_then(|txn| txn.position.take()) else {
+            return false;
+        };
+
+        self.pool.remove(&position);
+        true
+    }
+}
+
 impl<K, V, L, C, CV> Mempool<K, V, L, C, CV>
 where
     K: Eq + PartialEq + Hash + Clone + std::fmt::Debug,
-    V: Clone + std::fmt::Debug,
+    V: Clone + std::fmt::Debug + MintOrBurn,
     L: Eq + PartialEq + Hash + Clone + std::fmt::Debug,
-    C: Eq + PartialEq + Hash + Clone,
+    C: Eq + PartialEq + Hash + Clone + Into<Element>,
 {
     /// Add a transaction to the mempool, only adds key/txn if the key
     /// doesn't already exist in the mempool. This is used when other nodes
-    /// send us a txn they have received from a client
-    pub fn add(&self, key: K, txn: V, changes: Vec<C>) {
-        self._add(key, txn, changes, None);
+    /// send us a txn they have received from a client. Returns the key of the txn evicted to
+    /// make room for it, if any
+    pub fn add(&self, key: K, txn: V, changes: Vec<C>) -> Result<Option<K>, crate::Error> {
+        self._add(key, txn, changes, None)
     }
 
     /// Add a transaction to the mempool and wait for it to be committed. This will only
     /// be called where the txn is directly submitted to this node from a client
     // TODO: handle duplicate add_wait, so we properly await
     pub async fn add_wait(&self, key: K, txn: V, changes: Vec<C>) -> Result<CV, crate::Error> {
+        self.add_with_receiver(key, txn, changes)?
+            .0
+            .await
+            .expect("recv error")
+    }
+
+    /// Add a transaction to the mempool, returning a receiver that resolves once it is committed.
+    /// Unlike [`Mempool::add_wait`], this returns as soon as the txn has been added (or rejected),
+    /// along with the key of the txn evicted to make room for it, if any
+    #[allow(clippy::type_complexity)]
+    pub fn add_with_receiver(
+        &self,
+        key: K,
+        txn: V,
+        changes: Vec<C>,
+    ) -> Result<(oneshot::Receiver<Result<CV, crate::Error>>, Option<K>), crate::Error> {
         let (send, recv) = oneshot::channel::<Result<CV, crate::Error>>();
-        self._add(key, txn, changes, Some(send));
+        let evicted = self._add(key, txn, changes, Some(send))?;
 
-        recv.await.expect("recv error")
+        Ok((recv, evicted))
     }
 
     /// Internal add function, used by both add and add_wait
+    ///
+    /// Txns making a change already made by another txn in the mempool (e.g. spending the same
+    /// note) are rejected, as at most one of them could ever be included in a block.
+    ///
+    /// If the mempool is full, the last txn in the pool is evicted if the new txn
+    /// would be leased before it, otherwise the new txn is rejected. Returns the key of the
+    /// evicted txn, if any
     fn _add(
         &self,
         key: K,
         txn: V,
         changes: Vec<C>,
         sender: Option<oneshot::Sender<Result<CV, crate::Error>>>,
-    ) {
+    ) -> Result<Option<K>, crate::Error> {
         let mut state = self.state.lock();
 
         if state.txns.contains_key(&key) {
-            return;
+            return Ok(None);
         }
 
-        state.txns.entry(key.clone()).or_insert(MempoolTxn {
-            txn,
-            sender,
-            changes,
-        });
+        if let Some(change) = changes.iter().find(|c| state.changes.contains_key(c)) {
+            return Err(crate::Error::LeafAlreadyInMempool {
+                leaf: change.clone().into(),
+            });
+        }
+
+        let seq = state.next_seq;
+        let is_mint_or_burn = txn.is_mint_or_burn();
+
+        let mut evicted_key = None;
+        if state.txns.len() >= self.max_size {
+            let position = state.position_for(seq, self.ordering, is_mint_or_burn, false);
+
+            // Leased txns are never evicted, as they may already be in a proposal
+            let ranks_ahead = state
+                .pool
+                .last_key_value()
+                .map_or(false, |(last, _)| position < *last);
+
+            let evicted = match ranks_ahead {
+                true => state.pool.pop_last(),
+                false => None,
+            };
+
+            let Some((_, evicted)) = evicted else {
+                return Err(crate::Error::MempoolFull);
+            };
+
+            if let Some(txn) = state.remove(&evicted) {
+                if let Some(sender) = txn.sender {
+                    let _ = sender.send(Err(crate::Error::MempoolFull));
+                }
+
+                evicted_key = Some(evicted);
+            }
+        }
+
+        state.next_seq += 1;
+        for change in &changes {
+            state.changes.insert(change.clone(), key.clone());
+        }
+        state.txns.insert(
+            key.clone(),
+            MempoolTxn {
+                txn,
+                sender,
+                changes,
+                added_at: Instant::now(),
+                seq,
+                position: None,
+            },
+        );
 
         // Add the key to the pool
-        state.pool.push_back(key);
+        state.push(key, self.ordering, is_mint_or_burn, false);
+
+        Ok(evicted_key)
     }
 
     /// Commit a given transaction with key, removing it from the mempool
@@ -99,7 +313,7 @@ where
         let mut state = self.state.lock();
 
         for (key, result) in keys_with_results {
-            if let Some(mem_txn) = state.txns.remove(key) {
+            if let Some(mem_txn) = state.remove(key) {
                 if let Some(sender) = mem_txn.sender {
                     let _ = sender.send(result);
                 }
@@ -108,11 +322,6 @@ where
             if let Some(lease) = state.leased.get_mut(&lease) {
                 lease.remove(key);
             }
-
-            if let Some(pos) = state.pool.iter().position(|x| x == key) {
-                // TODO: this is very inefficient, we should find a better way to do this
-                state.pool.remove(pos);
-            }
         }
 
         // Drop lock before calling free with lock
@@ -128,15 +337,14 @@ where
         let mut state = self.state.lock();
 
         // Get the keys in the lease, and push them back into the pool, putting
-        // them first so they are highest priority
-        state
-            .leased
-            .remove(&lease)
-            .unwrap_or_default()
-            .into_iter()
-            .for_each(|k| {
-                state.pool.push_front(k);
-            });
+        // them first so they are highest priority (unless ordering by age)
+        for k in state.leased.remove(&lease).unwrap_or_default() {
+            let Some(is_mint_or_burn) = state.txns.get(&k).map(|t| t.txn.is_mint_or_burn()) else {
+                continue;
+            };
+
+            state.push(k, self.ordering, is_mint_or_burn, true);
+        }
     }
 
     /// Lease a specific key (based on another commit)
@@ -145,21 +353,14 @@ where
 
         for key in keys {
             // Remove from pool if exists
-            let k = state
-                .pool
-                .iter()
-                .position(|x| x == key)
-                .and_then(|p| state.pool.remove(p));
-
-            // Get an owned version of the key
-            let key = if let Some(k) = k { k } else { key.clone() };
+            state.take_from_pool(key);
 
             // Add it to the lease
             state
                 .leased
                 .entry(lease.clone())
                 .or_insert(HashSet::new())
-                .insert(key);
+                .insert(key.clone());
         }
     }
 
@@ -171,31 +372,25 @@ where
         let mut discard = vec![];
         let mut conflict_check = HashSet::new();
 
-        while let Some(key) = state.pool.pop_front() {
+        while let Some((position, key)) = state.pool.pop_first() {
             #[allow(clippy::expect_used)]
-            let changes = state
-                .txns
-                .get(&key)
-                .expect("key not found in txns")
-                .changes
-                .clone();
+            let txn = state.txns.get_mut(&key).expect("key not found in txns");
 
             // A change key has already been included in a previously added txn
-            if changes.iter().any(|c| conflict_check.contains(c)) {
-                discard.push(key);
+            if txn.changes.iter().any(|c| conflict_check.contains(c)) {
+                discard.push((position, key));
                 continue;
             }
 
+            txn.position = None;
+            conflict_check.extend(txn.changes.iter().cloned());
+            txns.push((key.clone(), txn.txn.clone()));
+
             state
                 .leased
                 .entry(lease.clone())
                 .or_insert(HashSet::new())
-                .insert(key.clone());
-
-            conflict_check.extend(changes);
-
-            #[allow(clippy::unwrap_used)]
-            txns.push((key.clone(), state.txns.get(&key).unwrap().txn.clone()));
+                .insert(key);
 
             // If we have reached the max count, break
             if txns.len() >= max_count {
@@ -203,11 +398,57 @@ where
             }
         }
 
-        // Return the discarded keys to the pool
+        // Return the discarded keys to the pool, in their original positions
         state.pool.extend(discard);
 
         txns
     }
+
+    /// The number of txns in the mempool, both pooled and leased
+    pub fn len(&self) -> usize {
+        self.state.lock().txns.len()
+    }
+
+    /// Whether a txn is in the mempool, either pooled or leased
+    pub fn contains(&self, key: &K) -> bool {
+        self.state.lock().txns.contains_key(key)
+    }
+
+    /// The changes which are already made by a txn in the mempool other than `key`, i.e. the
+    /// changes that would cause [`Mempool::add`] to fail with a conflict
+    pub fn conflicting_changes(&self, key: &K, changes: &[C]) -> Vec<C> {
+        let state = self.state.lock();
+
+        changes
+            .iter()
+            .filter(|c| state.changes.get(c).map_or(false, |other| other != key))
+            .cloned()
+            .collect()
+    }
+
+    /// List all txns in the mempool, pooled txns in the order they will be leased
+    /// followed by leased txns
+    pub fn list(&self) -> Vec<MempoolEntry<K>> {
+        let state = self.state.lock();
+        let now = Instant::now();
+
+        let entry = |key: &K, leased: bool| {
+            state.txns.get(key).map(|txn| MempoolEntry {
+                key: key.clone(),
+                age: now.saturating_duration_since(txn.added_at),
+                leased,
+            })
+        };
+
+        let pooled = state.pool.values().filter_map(|k| entry(k, false));
+        let leased = state
+            .leased
+            .values()
+            .flatten()
+            .filter_map(|k| entry(k, true));
+
+        pooled.chain(leased).collect()
+    }
 }
 
 #[cfg(test)]
@@ -217,12 +458,19 @@ mod tests {
     use super::*;
     use tokio::runtime::Runtime;
 
-    type Mp = Mempool<String, u32, usize, usize, ()>;
+    type Mp = Mempool<String, u32, usize, u64, ()>;
+
+    // Odd numbers are treated as mints/burns
+    impl MintOrBurn for u32 {
+        fn is_mint_or_burn(&self) -> bool {
+            self % 2 == 1
+        }
+    }
 
     #[test]
     fn test_add_txn() {
         let mempool = Mp::default();
-        mempool.add("key1".to_string(), 42, vec![]);
+        mempool.add("key1".to_string(), 42, vec![]).unwrap();
 
         {
             let state = mempool.state.lock();
@@ -230,7 +478,7 @@ mod tests {
             assert_eq!(state.txns.get("key1").unwrap().txn, 42);
         }
 
-        mempool.add("key1".to_string(), 24, vec![]);
+        mempool.add("key1".to_string(), 24, vec![]).unwrap();
 
         {
             let state = mempool.state.lock();
@@ -271,8 +519,8 @@ mod tests {
     #[test]
     fn test_commit_txn() {
         let mempool = Mp::default();
-        mempool.add("key1".into(), 42, vec![]);
-        mempool.add("key2".into(), 24, vec![]);
+        mempool.add("key1".into(), 42, vec![]).unwrap();
+        mempool.add("key2".into(), 24, vec![]).unwrap();
 
         mempool.commit(1, vec![(&"key1".to_string(), Ok(()))]);
 
@@ -286,9 +534,9 @@ mod tests {
     #[test]
     fn test_lease_batch() {
         let mempool = Mp::default();
-        mempool.add("key1".to_string(), 42, vec![]);
-        mempool.add("key2".to_string(), 24, vec![]);
-        mempool.add("key3".to_string(), 15, vec![]);
+        mempool.add("key1".to_string(), 42, vec![]).unwrap();
+        mempool.add("key2".to_string(), 24, vec![]).unwrap();
+        mempool.add("key3".to_string(), 15, vec![]).unwrap();
 
         let batch = mempool.lease_batch(2, 2);
         assert_eq!(batch.len(), 2);
@@ -308,27 +556,58 @@ mod tests {
     }
 
     #[test]
-    fn test_lease_with_duplicate_changes() {
+    fn test_add_with_duplicate_changes() {
         let mempool = Mp::default();
-        mempool.add("key1".to_string(), 42, vec![1, 2, 3]);
-        mempool.add("key2".to_string(), 24, vec![3, 4, 5]);
-        mempool.add("key3".to_string(), 15, vec![6, 7, 8]);
+        mempool.add("key1".to_string(), 42, vec![1, 2, 3]).unwrap();
+
+        let err = mempool
+            .add("key2".to_string(), 24, vec![3, 4, 5])
+            .unwrap_err();
+        assert!(
+            matches!(err, crate::Error::LeafAlreadyInMempool { leaf } if leaf == Element::from(3u64))
+        );
+
+        mempool.add("key3".to_string(), 15, vec![6, 7, 8]).unwrap();
 
         let batch = mempool.lease_batch(2, 3);
         assert_eq!(batch.len(), 2);
 
         {
             let state = mempool.state.lock();
-            assert_eq!(state.pool.len(), 1);
+            assert_eq!(state.pool.len(), 0);
+            assert!(state.txns.get("key2").is_none());
         }
+
+        // Once key1 is committed its changes can be made by another txn
+        mempool.commit(2, vec![(&"key1".to_string(), Ok(()))]);
+        mempool.add("key2".to_string(), 24, vec![3, 4, 5]).unwrap();
+    }
+
+    #[test]
+    fn test_conflicting_changes() {
+        let mempool = Mp::default();
+        mempool.add("key1".to_string(), 42, vec![1, 2, 3]).unwrap();
+
+        assert_eq!(
+            mempool.conflicting_changes(&"key2".to_string(), &[3, 4, 1]),
+            vec![3, 1]
+        );
+        assert!(mempool
+            .conflicting_changes(&"key2".to_string(), &[4, 5])
+            .is_empty());
+
+        // A txn doesn't conflict with itself
+        assert!(mempool
+            .conflicting_changes(&"key1".to_string(), &[1, 2, 3])
+            .is_empty());
     }
 
     #[test]
     fn test_partial_commit_followed_by_lease() {
         let mempool = Mp::default();
-        mempool.add("key1".to_string(), 1, vec![1]);
-        mempool.add("key2".to_string(), 2, vec![2]);
-        mempool.add("key3".to_string(), 3, vec![3]);
+        mempool.add("key1".to_string(), 1, vec![1]).unwrap();
+        mempool.add("key2".to_string(), 2, vec![2]).unwrap();
+        mempool.add("key3".to_string(), 3, vec![3]).unwrap();
 
         let batch = mempool.lease_batch(2, 3);
         assert_eq!(batch.len(), 3);
@@ -339,4 +618,103 @@ mod tests {
         let batch = mempool.lease_batch(2, 3);
         assert_eq!(batch.len(), 2);
     }
+
+    #[test]
+    fn test_full_mempool_rejects_lower_priority() {
+        let mempool = Mp::new(2, MempoolOrdering::Fifo);
+        mempool.add("key1".to_string(), 2, vec![]).unwrap();
+        mempool.add("key2".to_string(), 4, vec![]).unwrap();
+
+        let err = mempool.add("key3".to_string(), 6, vec![]).unwrap_err();
+        assert!(matches!(err, crate::Error::MempoolFull));
+
+        let state = mempool.state.lock();
+        assert_eq!(state.txns.len(), 2);
+        assert!(state.txns.get("key3").is_none());
+    }
+
+    #[test]
+    fn test_full_mempool_evicts_lower_priority() {
+        let mempool = Mp::new(2, MempoolOrdering::MintBurnFirst);
+        mempool.add("key1".to_string(), 2, vec![]).unwrap();
+        mempool.add("key2".to_string(), 4, vec![]).unwrap();
+
+        // Mints/burns are higher priority, so key2 (the last in the pool) is evicted
+        let evicted = mempool.add("key3".to_string(), 1, vec![]).unwrap();
+        assert_eq!(evicted, Some("key2".to_string()));
+
+        let batch = mempool.lease_batch(1, 3);
+        assert_eq!(
+            batch,
+            vec![("key3".to_string(), 1), ("key1".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn test_full_mempool_does_not_evict_leased() {
+        let mempool = Mp::new(1, MempoolOrdering::MintBurnFirst);
+        mempool.add("key1".to_string(), 2, vec![]).unwrap();
+        mempool.lease_batch(1, 1);
+
+        let err = mempool.add("key2".to_string(), 1, vec![]).unwrap_err();
+        assert!(matches!(err, crate::Error::MempoolFull));
+    }
+
+    #[test]
+    fn test_mint_burn_first_ordering() {
+        let mempool = Mp::new(10, MempoolOrdering::MintBurnFirst);
+        mempool.add("key1".to_string(), 2, vec![]).unwrap();
+        mempool.add("key2".to_string(), 3, vec![]).unwrap();
+        mempool.add("key3".to_string(), 4, vec![]).unwrap();
+        mempool.add("key4".to_string(), 5, vec![]).unwrap();
+
+        let keys = mempool
+            .lease_batch(1, 4)
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec!["key2", "key4", "key1", "key3"]);
+    }
+
+    #[test]
+    fn test_freed_txns_ordering() {
+        for (ordering, expected) in [
+            (MempoolOrdering::Fifo, vec!["key2", "key1", "key3"]),
+            (MempoolOrdering::Age, vec!["key1", "key2", "key3"]),
+        ] {
+            let mempool = Mp::new(10, ordering);
+            mempool.add("key1".to_string(), 2, vec![]).unwrap();
+            mempool.add("key2".to_string(), 4, vec![]).unwrap();
+            mempool.lease_txns(1, &["key2".to_string()]);
+            mempool.add("key3".to_string(), 6, vec![]).unwrap();
+
+            // Release key2 back into the pool
+            mempool.commit(1, vec![]);
+
+            let keys = mempool
+                .lease_batch(2, 3)
+                .into_iter()
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>();
+            assert_eq!(keys, expected, "{ordering:?}");
+        }
+    }
+
+    #[test]
+    fn test_list() {
+        let mempool = Mp::default();
+        mempool.add("key1".to_string(), 2, vec![]).unwrap();
+        mempool.add("key2".to_string(), 4, vec![]).unwrap();
+        mempool.lease_txns(1, &["key1".to_string()]);
+
+        let entries = mempool
+            .list()
+            .into_iter()
+            .map(|e| (e.key, e.leased))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            vec![("key2".to_string(), false), ("key1".to_string(), true)]
+        );
+    }
 }
@@ -0,0 +1,167 @@
+//! Node metrics, recorded with the global meter provider so they are served at `/metrics`
+//! (see [`rpc::metrics`]) and pushed to the OTLP collector if one is configured.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Once, OnceLock, Weak,
+    },
+    time::Duration,
+};
+
+use opentelemetry::{
+    metrics::{AsyncInstrument, Counter, Histogram},
+    KeyValue,
+};
+use parking_lot::RwLock;
+
+use crate::{types::BlockHeight, Error, NodeShared};
+
+pub(crate) struct Metrics {
+    commit_duration: Histogram<f64>,
+    txn_validation_failures: Counter<u64>,
+    rollup_gas: Counter<u64>,
+    /// Height of the block the prover worker is currently working on
+    prover_height: Arc<AtomicU64>,
+    /// The node observed by the metrics that are read from its state
+    node: Arc<RwLock<Weak<NodeShared>>>,
+    node_metrics_registered: Once,
+}
+
+/// The node's metrics. Must not be called before tracing is set up, otherwise the metrics are
+/// recorded with a no-op meter
+pub(crate) fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let meter = rpc::tracing::meter("node");
+
+        Self {
+            commit_duration: meter
+                .f64_histogram("node_commit_duration_seconds")
+                .with_description("Time taken to commit a block to the block store and notes tree")
+                .init(),
+            txn_validation_failures: meter
+                .u64_counter("node_txn_validation_failures")
+                .with_description("Txns that failed validation, by error kind")
+                .init(),
+            rollup_gas: meter
+                .u64_counter("node_rollup_gas_used")
+                .with_description("Gas used by rollup transactions sent by this prover")
+                .init(),
+            prover_height: Arc::new(AtomicU64::new(0)),
+            node: Arc::new(RwLock::new(Weak::new())),
+            node_metrics_registered: Once::new(),
+        }
+    }
+
+    pub(crate) fn record_commit(&self, duration: Duration) {
+        self.commit_duration.record(duration.as_secs_f64(), &[]);
+    }
+
+    pub(crate) fn record_txn_validation_failure(&self, err: &Error) {
+        let kind: &'static str = err.into();
+        self.txn_validation_failures
+            .add(1, &[KeyValue::new("kind", kind)]);
+    }
+
+    pub(crate) fn record_rollup_gas(&self, gas: u64) {
+        self.rollup_gas.add(gas, &[]);
+    }
+
+    pub(crate) fn set_prover_height(&self, height: BlockHeight) {
+        self.prover_height.store(height.0, Ordering::Relaxed);
+    }
+
+    /// Observe `node` in the metrics that are read from the node's state each time metrics are
+    /// collected. Holds a weak reference, so the node can still be dropped. The instruments are
+    /// only registered once, and observe the node registered last
+    pub(crate) fn register_node(&self, node: &Arc<NodeShared>) {
+        *self.node.write() = Arc::downgrade(node);
+        self.node_metrics_registered
+            .call_once(|| self.register_node_metrics());
+    }
+
+    fn register_node_metrics(&self) {
+        let meter = rpc::tracing::meter("node");
+
+        meter
+            .u64_observable_gauge("node_block_height")
+            .with_description("Height of the last committed block")
+            .with_callback(observe(&self.node, |node| node.height().0))
+            .init();
+
+        meter
+            .u64_observable_gauge("node_max_seen_height")
+            .with_description("Highest block height seen from peers")
+            .with_callback(observe(&self.node, |node| node.max_height().0))
+            .init();
+
+        meter
+            .u64_observable_gauge("node_mempool_size")
+            .with_description("Number of txns in the mempool, both pending and leased")
+            .with_callback(observe(&self.node, |node| node.mempool_len() as u64))
+            .init();
+
+        meter
+            .u64_observable_counter("node_smirk_hash_cache_hits")
+            .with_description("Notes tree hashes returned from the hash cache")
+            .with_callback(observe(&self.node, |node| {
+                node.notes_tree()
+                    .read()
+                    .tree()
+                    .cache()
+                    .metrics()
+                    .cache_hits() as u64
+            }))
+            .init();
+
+        meter
+            .u64_observable_counter("node_smirk_hash_cache_misses")
+            .with_description("Notes tree hashes that had to be computed")
+            .with_callback(observe(&self.node, |node| {
+                node.notes_tree()
+                    .read()
+                    .tree()
+                    .cache()
+                    .metrics()
+                    .cache_misses() as u64
+            }))
+            .init();
+
+        // Only observed when the node is a prover
+        let node = Arc::clone(&self.node);
+        let prover_height = Arc::clone(&self.prover_height);
+        meter
+            .u64_observable_gauge("node_prover_lag_blocks")
+            .with_description("Number of committed blocks the prover worker is behind by")
+            .with_callback(move |observer| {
+                let node = node.read().upgrade();
+                if let Some(node) = node.filter(|node| node.mode().is_prover()) {
+                    let lag = node
+                        .height()
+                        .0
+                        .saturating_sub(prover_height.load(Ordering::Relaxed));
+                    observer.observe(lag, &[]);
+                }
+            })
+            .init();
+    }
+}
+
+/// A callback for an observable instrument, which observes nothing once the node is dropped
+fn observe(
+    node: &Arc<RwLock<Weak<NodeShared>>>,
+    f: impl Fn(&NodeShared) -> u64 + Send + Sync + 'static,
+) -> impl Fn(&dyn AsyncInstrument<u64>) + Send + Sync + 'static {
+    let node = Arc::clone(node);
+    move |observer| {
+        let node = node.read().upgrade();
+        if let Some(node) = node {
+            observer.observe(f(&node), &[]);
+        }
+    }
+}
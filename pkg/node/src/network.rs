@@ -4,6 +4,11 @@ use crate::{block::Block, types::SnapshotId};
 use borsh::{BorshDeserialize, BorshSerialize};
 use derivative::Derivative;
 use doomslug::Approval;
+use libp2p::PeerId;
+use p2p2::{Gossip, MessageKind};
+use primitives::hash::CryptoHash;
+use primitives::peer::{Address, PeerIdSigner};
+use primitives::sig::Signature;
 use smirk::Element;
 
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
@@ -23,6 +28,12 @@ pub enum NetworkEvent {
 
     /// A chunk of blocks for the out of sync peer to apply.
     SnapshotChunk(SnapshotChunk),
+
+    /// Refuse a snapshot request, because we no longer have the requested blocks.
+    SnapshotRefusal(SnapshotRefusal),
+
+    /// Bind the sender's peer id to its address, sent when a connection is established.
+    PeerBinding(PeerBinding),
 }
 
 #[derive(Debug, Copy, Clone, BorshSerialize, BorshDeserialize)]
@@ -52,6 +63,13 @@ pub struct SnapshotAccept {
     pub kind: SnapshotKind,
 }
 
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct SnapshotRefusal {
+    pub snapshot_id: SnapshotId,
+    /// The lowest height we still have blocks for
+    pub earliest_height: BlockHeight,
+}
+
 #[derive(Derivative, Clone, BorshSerialize, BorshDeserialize)]
 #[derivative(Debug)]
 pub struct SnapshotChunkSlow {
@@ -65,6 +83,8 @@ pub struct SnapshotChunkSlow {
 pub struct SnapshotChunkFast {
     pub snapshot_id: SnapshotId,
     pub block: Option<Box<Block>>,
+    /// The block after `block`, whose endorsements make `block` final
+    pub successor: Option<Box<Block>>,
     /// Elements up to `block`
     #[derivative(Debug(format_with = "fmt_vec"))]
     pub elements: Vec<Element>,
@@ -85,6 +105,86 @@ impl SnapshotChunk {
     }
 }
 
+impl MessageKind for NetworkEvent {
+    fn kind(&self) -> &'static str {
+        match self {
+            NetworkEvent::Approval(_) => "approval",
+            NetworkEvent::Block(_) => "block",
+            NetworkEvent::Transaction(_) => "txn",
+            NetworkEvent::SnapshotRequest(_) => "snapshot-request",
+            NetworkEvent::SnapshotOffer(_) => "snapshot-offer",
+            NetworkEvent::SnapshotAccept(_) => "snapshot-accept",
+            NetworkEvent::SnapshotChunk(_) => "snapshot-chunk",
+            NetworkEvent::SnapshotRefusal(_) => "snapshot-refusal",
+            NetworkEvent::PeerBinding(_) => "peer-binding",
+        }
+    }
+}
+
+impl Gossip for NetworkEvent {
+    const TOPICS: &'static [&'static str] = &["blocks", "txns", "approvals"];
+
+    fn topic(&self) -> Option<&'static str> {
+        match self {
+            NetworkEvent::Block(_) => Some("blocks"),
+            NetworkEvent::Transaction(_) => Some("txns"),
+            NetworkEvent::Approval(_) => Some("approvals"),
+            _ => None,
+        }
+    }
+}
+
+/// A peer id signed by the address it belongs to, so messages from the peer can be attributed
+/// to the address
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct PeerBinding {
+    pub address: Address,
+    pub peer_id: Vec<u8>,
+    pub signature: Signature,
+}
+
+impl PeerBinding {
+    pub fn new(signer: &PeerIdSigner, peer_id: &PeerId) -> Self {
+        let peer_id = peer_id.to_bytes();
+
+        Self {
+            address: signer.address(),
+            signature: signer.sign(&Self::hash(&peer_id)),
+            peer_id,
+        }
+    }
+
+    /// Whether the binding is for `peer_id` and was signed by its address
+    pub fn verify(&self, peer_id: &PeerId) -> bool {
+        self.peer_id == peer_id.to_bytes()
+            && self.signature.verify(&Self::hash(&self.peer_id)) == Some(self.address.clone())
+    }
+
+    fn hash(peer_id: &[u8]) -> CryptoHash {
+        CryptoHash::from_vec_hash([b"peer-binding".as_slice(), peer_id].concat())
+    }
+}
+
 fn fmt_vec<T>(vec: &[T], fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
     write!(fmt, "Vec(len = {})", vec.len())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peer_binding() {
+        let signer = PeerIdSigner::default();
+        let peer_id = PeerId::random();
+
+        let binding = PeerBinding::new(&signer, &peer_id);
+        assert!(binding.verify(&peer_id));
+        assert!(!binding.verify(&PeerId::random()));
+
+        // Signed by another address
+        let mut forged = binding.clone();
+        forged.address = PeerIdSigner::default().address();
+        assert!(!forged.verify(&peer_id));
+    }
+}
@@ -1,8 +1,10 @@
-use crate::network::{NetworkEvent, SnapshotAccept, SnapshotOffer, SnapshotRequest};
+use crate::network::{
+    NetworkEvent, SnapshotAccept, SnapshotOffer, SnapshotRefusal, SnapshotRequest,
+};
 use crate::node::NodeShared;
 use eyre::Context;
 use libp2p::PeerId;
-use p2p2::Network;
+use p2p2::{MessageAcceptance, Misbehaviour, Network};
 use std::sync::Arc;
 use tokio::task::JoinHandle;
 
@@ -12,9 +14,25 @@ pub fn network_handler(
 ) -> JoinHandle<()> {
     tokio::spawn(async move {
         loop {
-            let Some((network_peer_id, event)) = network.next().await else { continue };
+            let Some((network_peer_id, event, gossip)) = network.next().await else { continue };
             tracing::debug!(network_peer_id = ?network_peer_id, event = ?event, "network event");
 
+            // Gossip is only handled, and forwarded to other peers, once it has been validated
+            if let Some(gossip) = gossip {
+                let acceptance = node.validate_gossip(&event);
+                let accepted = matches!(acceptance, MessageAcceptance::Accept);
+                if matches!(acceptance, MessageAcceptance::Reject) {
+                    node.report_misbehaviour(network_peer_id, rejected_misbehaviour(&event));
+                }
+                if let Err(e) = network.report_gossip(gossip, acceptance) {
+                    tracing::error!(error = ?e, "network error");
+                }
+
+                if !accepted {
+                    continue;
+                }
+            }
+
             if let Err(e) = handle_event(&node, network_peer_id, event).await {
                 tracing::error!(error = ?e, "network error");
             }
@@ -22,6 +40,19 @@ pub fn network_handler(
     })
 }
 
+/// Forget the address each peer is bound to once it disconnects, it sends its binding again
+/// when it reconnects
+pub fn disconnect_handler(
+    network: Arc<Network<NetworkEvent>>,
+    node: Arc<NodeShared>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Some(peer) = network.next_disconnected().await {
+            node.remove_peer_binding(&peer);
+        }
+    })
+}
+
 async fn handle_event(
     node: &NodeShared,
     peer: PeerId,
@@ -31,7 +62,7 @@ async fn handle_event(
 
     match event {
         NE::Approval(approval) => node
-            .receive_accept(&approval)
+            .receive_accept(peer, &approval)
             .await
             .context("Accept failed")?,
 
@@ -64,6 +95,13 @@ async fn handle_event(
             .receive_snapshot_chunk(peer, sc)
             .context("Snapshot chunk failed")?,
 
+        NE::SnapshotRefusal(SnapshotRefusal {
+            snapshot_id,
+            earliest_height,
+        }) => node
+            .receive_snapshot_refusal(peer, snapshot_id, earliest_height)
+            .context("Snapshot refusal failed")?,
+
         NE::SnapshotAccept(SnapshotAccept {
             snapshot_id,
             from_height,
@@ -73,7 +111,23 @@ async fn handle_event(
             .receive_snapshot_accept(peer, snapshot_id, from_height, to_height, kind)
             .await
             .context("Snapshot accept failed")?,
+
+        NE::PeerBinding(binding) => {
+            if let Err(err) = node.receive_peer_binding(peer, binding) {
+                node.report_misbehaviour(peer, Misbehaviour::InvalidMessage);
+                return Err(err).context("Peer binding failed");
+            }
+        }
     }
 
     Ok(())
 }
+
+/// The misbehaviour to count against the publisher of gossip that failed validation
+fn rejected_misbehaviour(event: &NetworkEvent) -> Misbehaviour {
+    match event {
+        NetworkEvent::Transaction(_) => Misbehaviour::InvalidProof,
+        NetworkEvent::Block(_) => Misbehaviour::InvalidBlock,
+        _ => Misbehaviour::InvalidMessage,
+    }
+}
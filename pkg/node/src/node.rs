@@ -1,24 +1,25 @@
 use crate::block::Block;
-use crate::cache::BlockCache;
-use crate::config::Config;
+use crate::cache::{BlockCache, VerifiedProofCache};
+use crate::config::{Config, HistoryMode};
 use crate::constants::{
-    MAX_BLOCK_PRODUCTION_DELAY, MAX_BLOCK_WAIT_DELAY, MERKLE_TREE_DEPTH, MIN_BLOCK_PRODUCTION_DELAY,
+    MAX_BLOCK_OVERHEAD_SIZE, MAX_BLOCK_PRODUCTION_DELAY, MAX_BLOCK_WAIT_DELAY, MAX_TXN_SIZE,
+    MERKLE_TREE_DEPTH, MIN_BLOCK_PRODUCTION_DELAY,
 };
 pub use crate::errors::Error;
 use crate::errors::Result;
-use crate::mempool::Mempool;
-use crate::network::NetworkEvent;
-use crate::network_handler::network_handler;
+use crate::mempool::{Mempool, MempoolEntry};
+use crate::network::{NetworkEvent, PeerBinding};
+use crate::network_handler::{disconnect_handler, network_handler};
 use crate::node::load::LoadedData;
 use crate::types::BlockHeight;
 use crate::utxo::UtxoProof;
 use crate::{sync, util};
-use block_store::{BlockListOrder, BlockStore, StoreList};
+use block_store::{Block as _, BlockListOrder, BlockStore, StoreList};
 use contracts::RollupContract;
-use doomslug::{Approval, ApprovalContent, ApprovalStake, ApprovalValidated, Doomslug};
+use doomslug::{Approval, ApprovalContent, ApprovalInner, ApprovalStake, Doomslug};
 use futures::Stream;
 use libp2p::PeerId;
-use p2p2::Network;
+use p2p2::{IpNet, Misbehaviour, Network};
 use parking_lot::{Mutex, RwLock};
 use primitives::hash::CryptoHash;
 use primitives::pagination::CursorChoice;
@@ -26,29 +27,35 @@ use primitives::peer::{self, Address, PeerIdSigner};
 use primitives::tick_worker::TickWorker;
 use prover::smirk_metadata::SmirkMetadata;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::net::IpAddr;
+use smirk::ExclusionProof;
+use std::collections::{HashMap, HashSet};
 use std::ops::RangeBounds;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tokio_stream::StreamExt;
-use tracing::{debug, error, info, instrument};
+use tracing::{error, info, instrument, warn};
 use zk_primitives::Element;
 
 pub use self::block_format::BlockFormat;
+pub use self::rejected_txn_format::RejectedTxn;
+pub use self::transaction::TxnStatus;
 pub use self::txn_format::TxnFormat;
 pub use self::txn_format::TxnMetadata;
 
 mod block;
 mod block_format;
+mod gossip;
 mod load;
 mod proposal;
+mod rejected_txn_format;
 mod snapshot;
 mod tick_worker;
 mod transaction;
+mod tree_snapshot;
 mod txn_format;
 
 pub type PersistentMerkleTree = smirk::storage::Persistent<MERKLE_TREE_DEPTH, SmirkMetadata>;
@@ -100,6 +107,9 @@ pub struct NodeShared {
     // Block cache (unconfirmed blocks)
     pub(crate) block_cache: Arc<Mutex<BlockCache>>,
 
+    /// Txns whose proofs have already been verified
+    verified_proofs: VerifiedProofCache,
+
     /// Store for Solid conesnsus blocks
     block_store: Arc<BlockStore<BlockFormat>>,
 
@@ -119,13 +129,11 @@ pub struct NodeShared {
     // Ticker
     pub(crate) ticker: TickWorker<NodeSharedArc>,
 
-    /// Explicitly whitelisted IP addresses
-    ///
-    /// Any IP address that isn't in this set will be banned (connections will be immediately
-    /// rejected)
-    ///
-    /// If empty, whitelisting is disabled (i.e. all IPs are allowed)
-    pub whitelisted_ips: HashSet<IpAddr>,
+    /// Whether blocks are being pruned in the background
+    pruning: Arc<AtomicBool>,
+
+    /// Whether a notes tree checkpoint is being written in the background
+    writing_checkpoint: Arc<AtomicBool>,
 }
 
 pub struct NodeSharedArc(Arc<NodeShared>);
@@ -137,6 +145,12 @@ pub struct NodeSharedState {
 
     /// Listeners
     listeners: Vec<mpsc::UnboundedSender<Arc<Block>>>,
+
+    /// What the sync worker is doing
+    sync_status: sync::SyncStatus,
+
+    /// Addresses of peers that have sent us a valid [`PeerBinding`]
+    peer_addresses: HashMap<PeerId, Address>,
 }
 
 impl Node {
@@ -152,6 +166,8 @@ impl Node {
             local_peer.address().to_hex()
         );
 
+        Self::check_validator_stakes(&config, &rollup_contract)?;
+
         let LoadedData {
             block_store,
             persistent_tree,
@@ -170,21 +186,34 @@ impl Node {
         let max_block_production_delay = Duration::from_secs(MAX_BLOCK_PRODUCTION_DELAY);
         let max_block_wait_delay = Duration::from_millis(MAX_BLOCK_WAIT_DELAY);
 
-        let doomslug = Arc::new(Mutex::new(Doomslug::new(
+        let mut doomslug = Doomslug::new(
             initial_block.content.header.height.0,
             min_block_production_delay,
             max_block_production_delay,
             max_block_production_delay / 10,
             max_block_wait_delay,
             doomslug::DoomslugThresholdMode::TwoThirds,
-        )));
+        );
+        // Blocks we've already committed are final
+        doomslug.on_block(
+            Instant::now(),
+            initial_block.hash(),
+            initial_block.content.header.height.0,
+            initial_block.content.header.height.0,
+        );
+        let doomslug = Arc::new(Mutex::new(doomslug));
 
-        let (keypair, _) = util::generate_p2p_key();
+        let keypair = util::load_or_generate_p2p_key(&config.db_path.join("p2p_key"))?;
+        let peer_binding = PeerBinding::new(&local_peer, &keypair.public().to_peer_id());
+        info!(peer_id = %keypair.public().to_peer_id(), "P2P identity");
+        // Blocks are the largest gossip messages
+        let max_gossip_size = config.block_txns_count * MAX_TXN_SIZE + MAX_BLOCK_OVERHEAD_SIZE;
         let network = Network::new(
             &keypair,
-            vec![config.p2p.laddr.clone()].into_iter(),
-            config.p2p.dial.clone().into_iter(),
-            config.p2p.whitelisted_ips.clone(),
+            &config.p2p,
+            Some(config.db_path.join("address_book.json")),
+            Some(NetworkEvent::PeerBinding(peer_binding)),
+            max_gossip_size,
         )?;
 
         let (sync_worker_sender, sync_worker_receiver) = mpsc::unbounded_channel();
@@ -192,9 +221,10 @@ impl Node {
         let node_shared = Arc::new(NodeShared {
             local_peer,
             rollup_contract: rollup_contract.clone(),
-            mempool: Mempool::default(),
+            mempool: Mempool::new(config.mempool_max_size, config.mempool_ordering),
             block_store,
             block_cache,
+            verified_proofs: VerifiedProofCache::new(config.verified_proof_cache_size),
             doomslug,
             notes_tree,
             network: Arc::new(network),
@@ -203,11 +233,16 @@ impl Node {
             state: Mutex::new(NodeSharedState {
                 last_commit: None,
                 listeners: vec![],
+                sync_status: sync::SyncStatus::Idle,
+                peer_addresses: HashMap::new(),
             }),
             sync_worker: sync::SyncWorkerChannel(sync_worker_sender.clone()),
-            whitelisted_ips: config.p2p.whitelisted_ips,
+            pruning: Arc::new(AtomicBool::new(false)),
+            writing_checkpoint: Arc::new(AtomicBool::new(false)),
         });
 
+        crate::metrics::metrics().register_node(&node_shared);
+
         let sync_worker = crate::sync::SyncWorker::new(
             Arc::clone(&node_shared),
             rollup_contract,
@@ -228,19 +263,8 @@ impl Node {
     pub async fn run(self) {
         let _network_event_handler =
             network_handler(self.shared.network.clone(), self.shared.clone());
-
-        // Dial peers
-        for peer in self.shared.config.p2p.dial.iter() {
-            debug!("Dialing peer {peer}...");
-            match self.shared.network.dial(peer.clone()).await {
-                Ok(_) => {
-                    info!("Connected to peer {}", peer)
-                }
-                Err(err) => {
-                    error!(?err, ?peer, "Failed to dial peer");
-                }
-            }
-        }
+        let _disconnect_handler =
+            disconnect_handler(self.shared.network.clone(), self.shared.clone());
 
         // Wait for the handlers
         tokio::select! {
@@ -289,11 +313,51 @@ impl NodeShared {
         }
 
         // Check if I am a validator in Ethereum for the given height
+        self.approvers_for_height(height)
+            .contains(&self.local_peer.address())
+    }
+
+    /// The validators for a block height, in the order they take turns to produce blocks
+    pub(crate) fn validators_for_height(&self, height: BlockHeight) -> Vec<Address> {
         self.rollup_contract
             .validators_for_height(height.0)
             .into_iter()
             .map(peer::Address::from)
-            .any(|p| self.local_peer.address() == p)
+            .collect()
+    }
+
+    /// Stakes of the validators for a block height, for counting approvals. At the last height
+    /// before a new validator set takes over, validators of both sets approve the block
+    pub(crate) fn approval_stakes(&self, height: BlockHeight) -> Vec<(ApprovalStake, bool)> {
+        self.rollup_contract
+            .validator_stakes_for_height(height.0)
+            .into_iter()
+            .map(|stake| {
+                (
+                    ApprovalStake {
+                        validator: peer::Address::from(stake.validator),
+                        stake_this_epoch: u128::try_from(stake.stake_this_epoch)
+                            .unwrap_or(u128::MAX),
+                        stake_next_epoch: u128::try_from(stake.stake_next_epoch)
+                            .unwrap_or(u128::MAX),
+                    },
+                    false,
+                )
+            })
+            .collect()
+    }
+
+    /// Hash of the validator stakes, see [`RollupContract::validator_stakes_hash`]
+    pub(crate) fn validator_stakes_hash(&self) -> contracts::H256 {
+        self.rollup_contract.validator_stakes_hash()
+    }
+
+    /// The validators whose approvals count for a block height, see [`Self::approval_stakes`]
+    pub(crate) fn approvers_for_height(&self, height: BlockHeight) -> Vec<Address> {
+        self.approval_stakes(height)
+            .into_iter()
+            .map(|(stake, _)| stake.validator)
+            .collect()
     }
 
     pub(crate) fn get_merkle_paths(&self, elements: &[Element]) -> Result<Vec<Vec<Element>>> {
@@ -314,6 +378,22 @@ impl NodeShared {
             .collect::<Result<Vec<Vec<Element>>>>()
     }
 
+    pub(crate) fn get_exclusion_proofs(
+        &self,
+        elements: &[Element],
+    ) -> Result<Vec<ExclusionProof<MERKLE_TREE_DEPTH>>> {
+        let notes_tree = self.notes_tree.read();
+
+        elements
+            .iter()
+            .map(|e| {
+                notes_tree
+                    .exclusion_proof(*e)
+                    .ok_or(Error::ElementInTree { element: *e })
+            })
+            .collect()
+    }
+
     pub(crate) async fn send_all(&self, event: NetworkEvent) {
         self.network.send_all(event).await
     }
@@ -322,6 +402,11 @@ impl NodeShared {
         self.network.send(&peer, request).await
     }
 
+    /// Txns waiting in the mempool, in the order they will be included in blocks
+    pub(crate) fn mempool_txns(&self) -> Vec<MempoolEntry<CryptoHash>> {
+        self.mempool.list()
+    }
+
     /// My peer address
     pub(crate) fn self_peer(&self) -> Address {
         self.local_peer.address()
@@ -350,9 +435,13 @@ impl NodeShared {
         Ok(())
     }
 
-    /// Receive an accept, if we're the leader
+    /// Receive an accept from `peer`, if we're the leader
     #[instrument(skip(self))]
-    pub(crate) async fn receive_accept(&self, approval_message: &Approval) -> Result<()> {
+    pub(crate) async fn receive_accept(
+        &self,
+        peer: PeerId,
+        approval_message: &Approval,
+    ) -> Result<()> {
         info!("Received approval");
 
         if self.config.mode != Mode::Validator {
@@ -361,50 +450,64 @@ impl NodeShared {
 
         // Check we are the block producer / leader for this accept height
         let target_height = BlockHeight(approval_message.content.target_height);
-        if self.get_leader_for_block_height(target_height) != self.self_peer() {
+        if self.get_leader_for_block_height(target_height) != Some(self.self_peer()) {
             return Ok(());
         }
 
-        // TODO:
-        // 1. Check if the accept is from a valid validator
-        // 2. Check if the accept is for the current proposal
-        // 3. Check if I am the leader of the accept
-        let approval: ApprovalValidated = approval_message.clone().try_into()?;
+        let approval = approval_message
+            .clone()
+            .validate(&self.approvers_for_height(target_height))?;
+
+        // The signature authenticates the validator, so approvals gossiped by peers we aren't
+        // bound to are accepted. A peer bound to another address is impersonating a validator
+        if let Some(bound) = self
+            .peer_address(&peer)
+            .filter(|bound| *bound != approval.validator)
+        {
+            return Err(Error::ApprovalFromWrongPeer {
+                peer: Box::new(peer),
+                validator: approval.validator,
+                bound,
+            });
+        }
 
-        // Get validator stakes
-        let stakes = self
-            .rollup_contract
-            .validators_for_height(approval_message.content.target_height)
-            .into_iter()
-            .map(|address| {
-                let address = Address::from(address);
-                //  &[(ApprovalStake, bool)],
-                (
-                    ApprovalStake {
-                        validator: address,
-                        stake_this_epoch: 1,
-                        stake_next_epoch: 1,
-                    },
-                    false,
-                )
-            })
-            .collect::<Vec<_>>();
+        let (height, last_block_hash) = {
+            let block_cache = self.block_cache.lock();
+            (block_cache.height(), *block_cache.hash())
+        };
 
-        // Add accept to Doomslug
-        self.doomslug
-            .lock()
-            .on_approval(Instant::now(), &approval, &stakes);
+        if target_height <= height {
+            // We've already committed this block
+            return Ok(());
+        }
+
+        // An endorsement of the next block must be for the block it will be built on. Approvals
+        // for later blocks are kept by doomslug until we've committed their previous block
+        if let ApprovalInner::Endorsement(endorsed) = &approval.content.inner {
+            if target_height == height.next() && *endorsed != last_block_hash {
+                return Err(Error::ApprovalNotForTip {
+                    target_height,
+                    expected: last_block_hash,
+                });
+            }
+        }
+
+        self.doomslug.lock().on_approval(
+            Instant::now(),
+            &approval,
+            &self.approval_stakes(target_height),
+        );
 
-        // TODO: check if we need to do something now, i.e. propose or send accept
+        // We may now have enough approvals to produce the block
+        self.ticker.tick();
 
         Ok(())
     }
 
-    pub fn get_leader_for_block_height(&self, height: BlockHeight) -> Address {
-        // TODO: will validators be in the same order for all nodes?
-        let validators = self.rollup_contract.validators_for_height(height.0);
-        let leader_index = height.0 % validators.len() as u64;
-        Address::from(validators[leader_index as usize])
+    /// The validator that produces the block at `height`. Validators take turns in the order of
+    /// the validator set in the rollup contract
+    pub fn get_leader_for_block_height(&self, height: BlockHeight) -> Option<Address> {
+        doomslug::block_producer(&self.validators_for_height(height), height.0).cloned()
     }
 
     pub(crate) async fn handle_out_of_sync(&self) -> Result<()> {
@@ -479,12 +582,18 @@ impl NodeShared {
         self.get_block(block_height)
     }
 
+    /// Register a listener which receives every block committed from now on
+    pub(crate) fn commit_listener(&self) -> mpsc::UnboundedReceiver<Arc<Block>> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.state.lock().listeners.push(tx);
+        rx
+    }
+
     pub(crate) async fn commit_stream(
         &self,
         from_height: Option<BlockHeight>,
     ) -> Pin<Box<dyn Stream<Item = Result<Arc<Block>>> + Send + '_>> {
-        let (tx, mut rx) = mpsc::unbounded_channel();
-        self.state.lock().listeners.push(tx);
+        let mut rx = self.commit_listener();
 
         let first_commit = rx.recv().await.unwrap();
         let rx_stream = UnboundedReceiverStream::new(rx).map(Ok);
@@ -518,10 +627,172 @@ impl NodeShared {
         Ok(txn.map(|TxnFormat::V1(txn, metadata)| (txn, metadata)))
     }
 
+    /// Get the txn that created or spent `leaf`
+    pub(crate) fn get_txn_by_leaf(&self, leaf: Element) -> Result<Option<(UtxoProof, TxnMetadata)>> {
+        let Some((height, index)) = self
+            .block_store
+            .get_txn_location_by_leaf(leaf.to_be_bytes())?
+        else {
+            return Ok(None);
+        };
+
+        let block = self
+            .get_block(height)?
+            .ok_or(Error::BlockNotFound { block: height })?;
+
+        Ok(block
+            .txns()
+            .into_iter()
+            .nth(index as usize)
+            .map(|TxnFormat::V1(txn, metadata)| (txn, metadata)))
+    }
+
+    /// Delete blocks older than the most recent `pruned-history-blocks` blocks, in a background
+    /// task so commits aren't blocked
+    pub(crate) fn prune_history(&self, height: BlockHeight) {
+        let before = BlockHeight(height.saturating_sub(self.config.pruned_history_blocks));
+
+        // Only one prune runs at a time, the next commit prunes anything this one missed
+        if self.pruning.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let block_store = Arc::clone(&self.block_store);
+        let pruning = Arc::clone(&self.pruning);
+        tokio::task::spawn_blocking(move || {
+            if let Err(err) = block_store.prune(before) {
+                error!(?err, "Failed to prune block history");
+            }
+
+            pruning.store(false, Ordering::Release);
+        });
+    }
+
+    /// Write a checkpoint of the notes tree, so the next restart doesn't have to rebuild it
+    /// from rocksdb. The tree is only locked while it's copied, the checkpoint is written in a
+    /// blocking task so commits aren't blocked
+    pub(crate) fn write_smirk_checkpoint(&self, height: BlockHeight) {
+        // Only one checkpoint is written at a time, the next interval writes a newer one
+        if self.writing_checkpoint.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let path = Node::smirk_checkpoint_path(&self.config);
+        let checkpoint = self.notes_tree.read().checkpoint(height.0);
+        let writing_checkpoint = Arc::clone(&self.writing_checkpoint);
+        tokio::task::spawn_blocking(move || {
+            if let Err(err) = checkpoint.write(path) {
+                error!(?err, "Failed to write notes tree checkpoint");
+            }
+
+            writing_checkpoint.store(false, Ordering::Release);
+        });
+    }
+
+    pub(crate) fn history_mode(&self) -> HistoryMode {
+        self.config.history_mode
+    }
+
+    /// Blocks below this height have been pruned and can't be served
+    pub(crate) fn pruned_height(&self) -> Result<Option<BlockHeight>> {
+        Ok(self.block_store.get_pruned_height()?)
+    }
+
     pub(crate) fn last_commit_time(&self) -> Option<Instant> {
         self.state.lock().last_commit
     }
 
+    pub(crate) fn mode(&self) -> Mode {
+        self.config.mode
+    }
+
+    pub(crate) fn sync_status(&self) -> sync::SyncStatus {
+        self.state.lock().sync_status
+    }
+
+    pub(crate) fn set_sync_status(&self, status: sync::SyncStatus) {
+        self.state.lock().sync_status = status;
+    }
+
+    pub(crate) fn connected_peers(&self) -> Vec<PeerId> {
+        self.network.connected_peers()
+    }
+
+    /// Replace the IP allow and deny lists, closing connections that are no longer allowed
+    pub(crate) fn set_ip_filter(
+        &self,
+        whitelisted_ips: HashSet<IpNet>,
+        denied_ips: HashSet<IpNet>,
+    ) -> Result<()> {
+        Ok(self.network.set_ip_filter(whitelisted_ips, denied_ips)?)
+    }
+
+    /// Count `misbehaviour` against `peer`, banning it once it has misbehaved too often
+    pub(crate) fn report_misbehaviour(&self, peer: PeerId, misbehaviour: Misbehaviour) {
+        warn!(%peer, ?misbehaviour, "Peer misbehaved");
+        if let Err(err) = self.network.report_misbehaviour(peer, misbehaviour) {
+            error!(?err, "Failed to report misbehaviour");
+        }
+    }
+
+    /// How much `peer` has misbehaved recently, 0 for a well behaved peer
+    pub(crate) fn peer_score(&self, peer: &PeerId) -> f64 {
+        self.network.peer_score(peer)
+    }
+
+    /// The address `peer` is bound to, if it has sent us a valid [`PeerBinding`]
+    pub(crate) fn peer_address(&self, peer: &PeerId) -> Option<Address> {
+        self.state.lock().peer_addresses.get(peer).cloned()
+    }
+
+    /// Bind `peer` to the address that signed `binding`
+    pub(crate) fn receive_peer_binding(&self, peer: PeerId, binding: PeerBinding) -> Result<()> {
+        if !binding.verify(&peer) {
+            return Err(Error::InvalidPeerBinding(Box::new(peer)));
+        }
+
+        info!(%peer, address = %binding.address, "Peer bound to address");
+        self.state
+            .lock()
+            .peer_addresses
+            .insert(peer, binding.address);
+
+        Ok(())
+    }
+
+    /// Forget the address `peer` is bound to, e.g. because it disconnected
+    pub(crate) fn remove_peer_binding(&self, peer: &PeerId) {
+        if let Some(address) = self.state.lock().peer_addresses.remove(peer) {
+            info!(%peer, %address, "Removed peer binding");
+        }
+    }
+
+    /// Number of txns in the mempool, both pending and leased
+    pub(crate) fn mempool_len(&self) -> usize {
+        self.mempool.len()
+    }
+
+    /// Number of elements in the notes tree
+    pub(crate) fn notes_tree_len(&self) -> usize {
+        self.notes_tree.read().tree().len()
+    }
+
+    /// The `(hash, height)` of the doomslug tip
+    pub(crate) fn doomslug_tip(&self) -> (CryptoHash, u64) {
+        self.doomslug.lock().get_tip()
+    }
+
+    /// The largest height of a block with doomslug finality, i.e. a block endorsed by more than
+    /// 2/3 of the validators in the block after it
+    pub(crate) fn doomslug_final_height(&self) -> BlockHeight {
+        BlockHeight(self.doomslug.lock().get_largest_final_height())
+    }
+
+    /// The height of the last block rolled up to the rollup contract
+    pub(crate) async fn rolled_up_height(&self) -> Result<BlockHeight> {
+        Ok(BlockHeight(self.rollup_contract.block_height().await?))
+    }
+
     pub fn estimate_block_time(height: BlockHeight, max_height: BlockHeight) -> u64 {
         chrono::Utc::now().timestamp() as u64 - (max_height.saturating_sub(height.0))
     }
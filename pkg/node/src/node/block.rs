@@ -1,3 +1,6 @@
+use std::collections::HashSet;
+
+use doomslug::Doomslug;
 use either::Either;
 use prover::smirk_metadata::SmirkMetadata;
 use smirk::{Batch, Element};
@@ -20,24 +23,94 @@ impl NodeShared {
             return Ok(());
         }
 
-        let validator = self.get_leader_for_block_height(block.content.header.height);
-
-        let signed_by = block
-            .signature
-            .verify(&block.hash())
-            .ok_or(Error::InvalidSignature)?;
-
-        if signed_by != validator {
+        if !self.is_signed_by_leader(block) {
             return Err(Error::InvalidSignature);
         }
 
-        block
-            .content
-            .validate(self.config.mode, &self.block_store, &self.notes_tree.read())?;
+        // The approvals must be from validators holding more than 2/3 of the stake. They endorse
+        // the block we're building on, or skip the heights after it if its successor never came
+        let height = block.content.header.height;
+        let parent_height = BlockHeight(self.doomslug_tip().1);
+        if !self.has_approval_threshold(block, parent_height) {
+            return Err(Error::InvalidBlockApproval { height });
+        }
+
+        block.content.validate(
+            self.config.mode,
+            &self.block_store,
+            &self.notes_tree.read(),
+            &self.verified_proofs,
+        )?;
 
         Ok(())
     }
 
+    /// Whether the block was signed by the leader for its height. Blocks in `bad-blocks` are
+    /// always accepted
+    pub(crate) fn is_signed_by_leader(&self, block: &Block) -> bool {
+        let height = block.content.header.height;
+        if self.config.bad_blocks.contains(&height) {
+            return true;
+        }
+
+        let Some(leader) = self.get_leader_for_block_height(height) else {
+            return false;
+        };
+        block.signature.verify(&block.hash()) == Some(leader)
+    }
+
+    /// Whether all of the block's approvals are from validators for its height, and they hold
+    /// more than 2/3 of the stake
+    fn has_approval_threshold(&self, block: &Block, parent_height: BlockHeight) -> bool {
+        let height = block.content.header.height;
+        let validators = self.approvers_for_height(height);
+        let approvers = block.content.header.approvers(parent_height);
+        let all_approvers_valid = approvers.iter().all(|approver| {
+            approver
+                .as_ref()
+                .map_or(false, |approver| validators.contains(approver))
+        });
+        let approvers = approvers.into_iter().flatten().collect::<HashSet<_>>();
+
+        all_approvers_valid
+            && Doomslug::has_finality_threshold(&approvers, &self.approval_stakes(height))
+    }
+
+    /// Whether `block` is final because `successor`, the block at the next height, was signed by
+    /// its leader and carries endorsements of `block` by more than 2/3 of the stake
+    pub(crate) fn is_final_with_successor(&self, block: &Block, successor: &Block) -> bool {
+        let height = block.content.header.height;
+
+        successor.content.header.height == height.next()
+            && successor.content.header.last_block_hash == block.hash()
+            && self.is_signed_by_leader(successor)
+            && self.has_approval_threshold(successor, height)
+    }
+
+    /// The largest doomslug final height once `block` is committed. The previous block is final
+    /// if `block` contains its endorsements by more than 2/3 of the validators. Skips don't make
+    /// a block final
+    pub(super) fn final_height_with(&self, block: &Block) -> BlockHeight {
+        let header = &block.content.header;
+        let final_height = self.doomslug_final_height();
+
+        let parent_height = BlockHeight(self.doomslug_tip().1);
+        if header.height != parent_height.next() {
+            return final_height;
+        }
+
+        let approvers = header
+            .approvers(parent_height)
+            .into_iter()
+            .flatten()
+            .collect::<HashSet<_>>();
+        if !Doomslug::has_finality_threshold(&approvers, &self.approval_stakes(header.height)) {
+            return final_height;
+        }
+
+        final_height.max(parent_height)
+    }
+
     #[instrument(skip_all)]
     pub(crate) fn apply_block_to_tree(
         notes_tree: &mut PersistentMerkleTree,
@@ -0,0 +1,53 @@
+use p2p2::MessageAcceptance;
+use tracing::warn;
+
+use crate::{network::NetworkEvent, types::BlockHeight, utxo::verify_txn_proof, NodeShared};
+
+impl NodeShared {
+    /// Check a gossip event before it is handled and forwarded to other peers. This only checks
+    /// that the event is authentic (e.g. a block is signed by its leader), the rest of the
+    /// validation depends on our state and is done when the event is handled
+    pub(crate) fn validate_gossip(&self, event: &NetworkEvent) -> MessageAcceptance {
+        match event {
+            NetworkEvent::Approval(approval) => {
+                let target_height = BlockHeight(approval.content.target_height);
+                if target_height <= self.height() {
+                    return MessageAcceptance::Ignore;
+                }
+
+                match approval
+                    .clone()
+                    .validate(&self.approvers_for_height(target_height))
+                {
+                    Ok(_) => MessageAcceptance::Accept,
+                    Err(err) => {
+                        warn!(?err, "Invalid approval gossip");
+                        MessageAcceptance::Reject
+                    }
+                }
+            }
+            NetworkEvent::Block(block) => {
+                if block.content.header.height <= self.height() {
+                    MessageAcceptance::Ignore
+                } else if self.is_signed_by_leader(block) {
+                    MessageAcceptance::Accept
+                } else {
+                    warn!(height = ?block.content.header.height, "Block gossip not signed by leader");
+                    MessageAcceptance::Reject
+                }
+            }
+            NetworkEvent::Transaction(txn) => {
+                if self.mempool.contains(&txn.hash()) {
+                    MessageAcceptance::Ignore
+                } else if verify_txn_proof(txn, &self.verified_proofs) {
+                    MessageAcceptance::Accept
+                } else {
+                    warn!(txn = ?txn.hash(), "Txn gossip has an invalid proof");
+                    MessageAcceptance::Reject
+                }
+            }
+            // Other events are sent directly to peers, never gossiped
+            _ => MessageAcceptance::Reject,
+        }
+    }
+}
@@ -1,12 +1,16 @@
-use std::{path::Path, sync::OnceLock};
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 
 use block_store::BlockStore;
+use contracts::RollupContract;
 use smirk::Element;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::{
     block::Block, config::Config, constants::MERKLE_TREE_DEPTH, types::BlockHeight, BlockFormat,
-    Node, NodeShared, PersistentMerkleTree, Result,
+    Error, Node, NodeShared, PersistentMerkleTree, Result,
 };
 
 pub(super) struct LoadedData {
@@ -21,6 +25,35 @@ fn empty_tree_hash() -> Element {
 }
 
 impl Node {
+    /// Check the validator stakes from `validator-stakes` only list validators in the rollup
+    /// contract, and match `validator-stakes-hash` if it's set. The stakes decide which blocks
+    /// are final, so a validator using different stakes would fork from the others
+    pub(super) fn check_validator_stakes(config: &Config, contract: &RollupContract) -> Result<()> {
+        let stakes_hash = contract.validator_stakes_hash();
+        info!(hash = ?stakes_hash, stakes = ?contract.validator_stakes(), "Validator stakes");
+
+        for (validator, stake) in contract.validator_stakes() {
+            if !contract.is_known_validator(validator) {
+                return Err(Error::UnknownValidatorStake(*validator));
+            }
+
+            if stake.is_zero() {
+                return Err(Error::ZeroValidatorStake(*validator));
+            }
+        }
+
+        if let Some(expected) = config.validator_stakes_hash {
+            if expected != stakes_hash {
+                return Err(Error::ValidatorStakesHashMismatch {
+                    expected,
+                    got: stakes_hash,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     pub(super) fn load_db_and_smirk(config: &Config) -> Result<LoadedData> {
         let db_path = &config.db_path.join("latest");
         info!("Loading DB from: {}", db_path.to_str().unwrap());
@@ -29,7 +62,11 @@ impl Node {
         info!("Loading Smirk from: {}", &smirk_path.to_str().unwrap());
 
         let block_store = BlockStore::create_or_load(db_path)?;
-        let mut persistent_tree = smirk::storage::Persistent::load(&smirk_path)?;
+        let mut persistent_tree =
+            match Self::load_smirk_from_checkpoint(config, &block_store, &smirk_path)? {
+                Some(persistent_tree) => persistent_tree,
+                None => smirk::storage::Persistent::load(&smirk_path)?,
+            };
 
         let Some(max_height) = block_store.get_max_height()? else {
             info!(
@@ -122,6 +159,79 @@ impl Node {
         Ok(data)
     }
 
+    pub(crate) fn smirk_checkpoint_path(config: &Config) -> PathBuf {
+        config.smirk_path.join("checkpoint")
+    }
+
+    /// Load the notes tree from the last checkpoint, and apply any blocks committed since.
+    /// Returns `None` if there is no usable checkpoint, in which case the tree should be
+    /// loaded from rocksdb instead
+    fn load_smirk_from_checkpoint(
+        config: &Config,
+        block_store: &BlockStore<BlockFormat>,
+        smirk_path: &Path,
+    ) -> Result<Option<PersistentMerkleTree>> {
+        let checkpoint_path = Self::smirk_checkpoint_path(config);
+        if !checkpoint_path.exists() {
+            return Ok(None);
+        }
+
+        let Some(max_height) = block_store.get_max_height()? else {
+            return Ok(None);
+        };
+
+        let (mut persistent_tree, checkpoint_height) =
+            match PersistentMerkleTree::load_from_checkpoint(smirk_path, &checkpoint_path) {
+                Ok(loaded) => loaded,
+                Err(err) => {
+                    warn!(?err, "Failed to load notes tree checkpoint");
+                    return Ok(None);
+                }
+            };
+
+        if checkpoint_height > max_height.0 {
+            warn!(
+                checkpoint_height,
+                ?max_height,
+                "Notes tree checkpoint is ahead of the block store"
+            );
+            return Ok(None);
+        }
+
+        // Blocks after the checkpoint haven't been applied yet
+        for height in checkpoint_height + 1..=max_height.0 {
+            let Some(block) = block_store.get(BlockHeight(height))? else {
+                warn!(height, "Block after notes tree checkpoint is missing");
+                return Ok(None);
+            };
+
+            NodeShared::apply_block_to_tree(
+                &mut persistent_tree,
+                &block.into_block().content.state,
+                BlockHeight(height),
+                true,
+            )?;
+        }
+
+        let block = block_store
+            .get(max_height)?
+            .ok_or(Error::BlockNotFound { block: max_height })?
+            .into_block();
+        if persistent_tree.tree().root_hash() != block.content.state.root_hash {
+            warn!(
+                checkpoint_height,
+                local_tree_root_hash = ?persistent_tree.tree().root_hash(),
+                block_root_hash = ?block.content.state.root_hash,
+                "Notes tree checkpoint does not match the block store"
+            );
+            return Ok(None);
+        }
+
+        info!(checkpoint_height, "Loaded notes tree from checkpoint");
+
+        Ok(Some(persistent_tree))
+    }
+
     /// Moves current db and smirk to old-{unix-timestamp-millis}-{random}
     fn reset_db_and_smirk(db_path: Option<&Path>, smirk_path: Option<&Path>) -> Result<()> {
         let timestamp = chrono::Utc::now().timestamp_millis();
@@ -138,6 +248,12 @@ impl Node {
             let new_smirk_path = smirk_path.join(&new_dir_name);
             std::fs::rename(smirk_path.join("latest"), &new_smirk_path)?;
             info!("Moved smirk to {:?}", new_smirk_path);
+
+            // The checkpoint is of the old tree
+            let checkpoint_path = smirk_path.join("checkpoint");
+            if checkpoint_path.exists() {
+                std::fs::remove_file(checkpoint_path)?;
+            }
         }
 
         Ok(())
@@ -6,12 +6,13 @@ use smirk::Element;
 use tracing::{info, instrument, warn};
 
 use crate::{
-    block::{Block, BlockContent, BlockHeader, BlockState}, network::NetworkEvent, node::block_format::BlockMetadata, types::BlockHeight, BlockFormat, Error, Mode, NodeShared, Result
+    block::{Block, BlockContent, BlockHeader, BlockState}, config::HistoryMode, metrics::metrics, network::NetworkEvent, node::block_format::BlockMetadata, types::BlockHeight, utxo::UtxoProof, BlockFormat, Error, NodeShared, Result
 };
 
 impl NodeShared {
     #[instrument(skip(self))]
    pub(crate) fn commit_proposal(&self, block: Block) -> Result<()> {
+        let start = Instant::now();
         let state = &block.content.state;
         let height = block.content.header.height;
 
@@ -72,6 +73,13 @@ impl NodeShared {
 
         Self::apply_block_to_tree(&mut self.notes_tree.write(), state, height, skip_validation)?;
 
+        // Move doomslug to the new tip, which schedules our endorsement of this block for the
+        // producer of the next block
+        let final_height = self.final_height_with(&block);
+        self.doomslug
+            .lock()
+            .on_block(Instant::now(), block.hash(), height.0, final_height.0);
+
         let block = Arc::new(block);
 
         // Commit changes in mempool (releasing unused txns and removing used ones). This will
@@ -79,19 +87,26 @@ impl NodeShared {
         self.mempool
             .commit(height, keys.iter().map(|k| (k, Ok(Arc::clone(&block)))).collect());
 
+        if self.config.history_mode == HistoryMode::Pruned {
+            self.prune_history(height);
+        }
+
+        let checkpoint_interval = self.config.smirk_checkpoint_interval;
+        if checkpoint_interval > 0 && height.0 % checkpoint_interval == 0 {
+            self.write_smirk_checkpoint(height);
+        }
+
         // Notify any commit listeners
         let listeners = &mut self.state.lock().listeners;
         listeners.retain(|tx| tx.send(Arc::clone(&block)).is_ok());
 
+        metrics().record_commit(start.elapsed());
+
         Ok(())
     } 
 
    #[instrument(skip(self))]
    pub fn receive_proposal(&self, block: Block) -> Result<()> {
-        if self.config.mode == Mode::Validator {
-            panic!("This function should not be called by the validator");
-        }
-
         let manifest_height = block.content.header.height;
         let keys = &block
             .content
@@ -133,11 +148,30 @@ impl NodeShared {
                 .mempool
                 .lease_batch(height, self.config.block_txns_count);
 
+            // Verifying every proof in one batch is much cheaper than verifying them one by one.
+            // If the batch fails, verify each proof individually to find the invalid one
+            let unverified = utxos
+                .iter()
+                .map(|(_, txn)| txn)
+                .filter(|txn| !self.verified_proofs.contains(txn))
+                .collect::<Vec<_>>();
+            let proofs_verified = UtxoProof::verify_batch(unverified.iter().copied());
+            if proofs_verified {
+                for txn in unverified {
+                    self.verified_proofs.insert(txn);
+                }
+            }
+
             for (_, txn) in &utxos {
-                if let Err(err) = self.validate_transaction(txn).await {
+                let result = match proofs_verified {
+                    true => self.validate_transaction_state(txn).await,
+                    false => self.validate_transaction(txn).await,
+                };
+
+                if let Err(err) = result {
                     let txn_hash = txn.hash();
                     // commit releases the other keys in the lease too
-                    self.mempool.commit(height, vec![(&txn_hash, Err(err))]);
+                    self.commit_to_mempool(height, vec![(&txn_hash, Err(err))]);
 
                     // If any of the transactions fail validation,
                     // return early and try other transactions in a new proposal.
@@ -186,7 +220,7 @@ impl NodeShared {
                 let txn_hash = *txn_hash;
                 let failing_txn_hash = *failing_txn_hash;
 
-                self.mempool.commit(
+                self.commit_to_mempool(
                     height,
                     vec![(
                         &failing_txn_hash,
@@ -205,7 +239,7 @@ impl NodeShared {
                 let spent_note = *spent_note;
                 let failing_txn_hash = *failing_txn_hash;
 
-                self.mempool.commit(
+                self.commit_to_mempool(
                     height,
                     vec![(
                         &failing_txn_hash,
@@ -221,7 +255,7 @@ impl NodeShared {
                 let recent_roots = recent_roots.clone();
                 let txn_hash = *txn_hash;
 
-                self.mempool.commit(
+                self.commit_to_mempool(
                     height,
                     vec![(
                         &txn_hash,
@@ -248,7 +282,7 @@ impl NodeShared {
                     .map(|tx| tx.hash())
                     .collect::<Vec<_>>();
 
-                self.mempool.commit(
+                self.commit_to_mempool(
                     height,
                     txn_errors
                         .into_iter()
@@ -0,0 +1,40 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use primitives::block_height::BlockHeight;
+use serde::{Deserialize, Serialize};
+use wire_message::WireMessage;
+
+/// Why and when a txn was rejected by this node
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+pub struct RejectedTxn {
+    /// The kind of error, e.g. `note-already-spent`
+    pub kind: String,
+    /// The error message
+    pub reason: String,
+    /// Height of the node when the txn was rejected
+    pub height: BlockHeight,
+    /// Unix timestamp (in seconds) when the txn was rejected
+    pub time: u64,
+}
+
+#[derive(Debug, Clone)]
+#[wire_message::wire_message]
+pub enum RejectedTxnFormat {
+    V1(RejectedTxn),
+}
+
+impl WireMessage for RejectedTxnFormat {
+    type Ctx = ();
+    type Err = core::convert::Infallible;
+
+    fn version(&self) -> u64 {
+        match self {
+            Self::V1(_) => 1,
+        }
+    }
+
+    fn upgrade_once(self, _ctx: &mut Self::Ctx) -> Result<Self, wire_message::Error> {
+        match self {
+            Self::V1(_) => Err(Self::max_version_error()),
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use libp2p::PeerId;
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
 
 use crate::{
     network::{SnapshotChunk, SnapshotKind},
@@ -25,6 +25,21 @@ impl NodeShared {
         Ok(())
     }
 
+    /// A node refused to send us a snapshot, because it has pruned the blocks we need
+    #[instrument(skip(self))]
+    pub(crate) fn receive_snapshot_refusal(
+        &self,
+        peer: PeerId,
+        snapshot_id: SnapshotId,
+        earliest_height: BlockHeight,
+    ) -> Result<()> {
+        warn!("Peer refused snapshot request, it only has blocks from {earliest_height:?}");
+        self.sync_worker
+            .snapshot_refusal(peer, snapshot_id, earliest_height)?;
+
+        Ok(())
+    }
+
     /// A node is sending us a snapshot chunk
     #[instrument(skip(self))]
     pub(crate) fn receive_snapshot_chunk(&self, peer: PeerId, sc: SnapshotChunk) -> Result<()> {
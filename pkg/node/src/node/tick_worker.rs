@@ -1,44 +1,48 @@
 use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
-use doomslug::ApprovalContent;
-use primitives::tick_worker::TickWorkerTick;
+use doomslug::{ApprovalContent, ApprovalValidated};
+use primitives::{hash::CryptoHash, tick_worker::TickWorkerTick};
 use tracing::{error, warn};
 
-use crate::{types::BlockHeight, Mode, NodeSharedArc};
+use crate::{types::BlockHeight, Mode, NodeShared, NodeSharedArc};
+
+/// How often a validator checks doomslug's timer for approvals to send, and whether it has
+/// enough approvals to produce a block
+const APPROVAL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
 
 #[async_trait]
 impl TickWorkerTick for NodeSharedArc {
     async fn tick(&self) -> Option<Instant> {
         let node = &self.0;
 
+        // This block may mean we can commit some proposals
+        loop {
+            let next = node.block_cache.lock().get_next_commit_block();
+            let Some(block) = next else {
+                break;
+            };
+            if let Err(err) = node.validate_block(&block) {
+                error!(?err, ?block, "Error validating block");
+                node.block_cache.lock().remove(&block.hash());
+                continue;
+            };
+            node.block_cache.lock().confirm(block.content.header.height);
+            match node.commit_proposal(block.clone()) {
+                Ok(_) => {}
+                Err(err) => {
+                    error!("Unable to commit proposal: {}", err);
+                    return None;
+                }
+            }
+        }
+
         // Get current height
         let height = node.block_cache.lock().height();
         let target_height = height + BlockHeight(1);
 
-        // Check if I am the single validator
+        // Check if I am a validator
         if !node.is_validator_for_height(target_height) {
-            // This block may mean we can commit some proposals
-            loop {
-                let next = node.block_cache.lock().get_next_commit_block();
-                let Some(block) = next else {
-                    break;
-                };
-                if let Err(err) = node.validate_block(&block) {
-                    error!(?err, ?block, "Error validating block");
-                    node.block_cache.lock().remove(&block.hash());
-                    continue;
-                };
-                node.block_cache.lock().confirm(block.content.header.height);
-                match node.commit_proposal(block.clone()) {
-                    Ok(_) => {}
-                    Err(err) => {
-                        error!("Unable to commit proposal: {}", err);
-                        return None;
-                    }
-                }
-            }
-
             if node.is_out_of_sync() {
                 if let Err(err) = node.handle_out_of_sync().await {
                     error!(?err, "Error syncing");
@@ -67,14 +71,35 @@ impl TickWorkerTick for NodeSharedArc {
             return None;
         }
 
-        let last_confirmed = *node.block_cache.lock().hash();
         let start_time = Instant::now();
 
-        let approval = ApprovalContent::new_endorsement(&last_confirmed, target_height.0)
-            .to_approval_validated(&node.local_peer);
+        // Send the approvals that are due: an endorsement of the block we last committed, or a
+        // skip if the next block is taking too long. Our own approvals count towards our blocks,
+        // but aren't sent to us over the network
+        let approvals = node.doomslug.lock().process_timer(start_time);
+        for approval in approvals {
+            let own_approval = approval.to_approval_validated(&node.local_peer);
+            let stakes = node.approval_stakes(BlockHeight(approval.target_height));
+            node.doomslug
+                .lock()
+                .on_approval(start_time, &own_approval, &stakes);
+
+            if let Err(err) = node.send_accept(approval).await {
+                error!(?err, "Error sending approval");
+            }
+        }
+
+        let last_confirmed = *node.block_cache.lock().hash();
+        let Some((target_height, approvals)) =
+            ready_target_height(node, height, last_confirmed, start_time)
+        else {
+            // Wait for more than 2/3 of the validators to approve a block we produce. Receiving
+            // an approval also wakes the ticker
+            return Some(start_time + APPROVAL_CHECK_INTERVAL);
+        };
 
         match node
-            .create_proposal(last_confirmed, BlockHeight(height.0 + 1), vec![approval])
+            .create_proposal(last_confirmed, target_height, approvals)
             .await
         {
             Ok(_) => {}
@@ -87,3 +112,213 @@ impl TickWorkerTick for NodeSharedArc {
         Some(next_time)
     }
 }
+
+/// The lowest height we're the leader for that has enough approvals to build on the block at
+/// `height`, with those approvals. The leader for the next height builds on it once more than
+/// 2/3 of the validators endorse it. If that leader is offline, validators send skips to the
+/// leaders of later heights instead, so the chain doesn't halt
+fn ready_target_height(
+    node: &NodeShared,
+    height: BlockHeight,
+    last_confirmed: CryptoHash,
+    now: Instant,
+) -> Option<(BlockHeight, Vec<ApprovalValidated>)> {
+    let next_height = height.next();
+    let max_target_height = {
+        let doomslug = node.doomslug.lock();
+        BlockHeight(
+            doomslug
+                .get_largest_approval_target_height()
+                .max(doomslug.get_largest_sent_target_height()),
+        )
+    };
+
+    (next_height.0..=max_target_height.0.max(next_height.0))
+        .map(BlockHeight)
+        .filter(|target_height| {
+            node.get_leader_for_block_height(*target_height) == Some(node.self_peer())
+        })
+        .find_map(|target_height| {
+            // Our endorsement is only sent once doomslug's endorsement delay has passed, but we
+            // don't need to wait for it to build on the block ourselves
+            let endorsement = (target_height == next_height).then(|| {
+                ApprovalContent::new_endorsement(&last_confirmed, target_height.0)
+                    .to_approval_validated(&node.local_peer)
+            });
+            let stakes = node.approval_stakes(target_height);
+
+            let mut doomslug = node.doomslug.lock();
+            if let Some(endorsement) = &endorsement {
+                doomslug.on_approval(now, endorsement, &stakes);
+            }
+
+            if !doomslug.ready_to_produce_block(now, target_height.0, true, false) {
+                return None;
+            }
+
+            let approvals = doomslug
+                .get_witness(&last_confirmed, height.0, target_height.0)
+                .into_values()
+                .map(|(approval, _)| approval)
+                .collect::<Vec<_>>();
+            Some((target_height, approvals))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use clap::Parser;
+    use contracts::{Client, RollupContract, ValidatorSet, H256, U256};
+    use libp2p::PeerId;
+    use primitives::peer::PeerIdSigner;
+    use tempdir::TempDir;
+
+    use crate::{
+        block::Block,
+        config::{cli::CliArgs, Config},
+        network::PeerBinding,
+        Error, Node,
+    };
+
+    use super::*;
+
+    struct TestValidator {
+        _dir: TempDir,
+        signer: PeerIdSigner,
+        peer_id: PeerId,
+        node: Arc<NodeShared>,
+    }
+
+    impl TestValidator {
+        async fn tick(&self) {
+            NodeSharedArc(Arc::clone(&self.node)).tick().await;
+        }
+    }
+
+    fn signer(n: u8) -> PeerIdSigner {
+        PeerIdSigner::new(secp256k1::SecretKey::from_slice(&[n; 32]).unwrap())
+    }
+
+    /// A rollup contract with `signers` as its only validator set, that is never called
+    fn rollup_contract(signers: &[PeerIdSigner]) -> RollupContract {
+        let client = Client::new("http://127.0.0.1:1", None);
+        let address = "0000000000000000000000000000000000000000";
+        let contract = client
+            .load_contract_from_str(address, r#"{ "abi": [] }"#)
+            .unwrap();
+        let rollup_contract = RollupContract::new(
+            client,
+            contract,
+            contracts::SecretKey::from_slice(&[1; 32]).unwrap(),
+            H256::zero(),
+            address.parse().unwrap(),
+        );
+
+        *rollup_contract.validator_sets.write() = vec![ValidatorSet {
+            validators: signers
+                .iter()
+                .map(|signer| contracts::Address::from_slice(&signer.address().to_bytes()))
+                .collect(),
+            stakes: vec![U256::one(); signers.len()],
+            valid_from: U256::zero(),
+        }];
+
+        rollup_contract
+    }
+
+    fn validator(signer: PeerIdSigner, rollup_contract: RollupContract) -> TestValidator {
+        let dir = TempDir::new("tick_worker").unwrap();
+
+        let mut config = Config::from_env(CliArgs::try_parse_from(["node"]).unwrap()).unwrap();
+        config.mode = Mode::Validator;
+        config.db_path = dir.path().join("db");
+        config.smirk_path = dir.path().join("smirk");
+        config.p2p.laddr = "/ip4/127.0.0.1/tcp/0".parse().unwrap();
+        config.p2p.dial = vec![];
+
+        let node = Node::new(signer.clone(), rollup_contract, config).unwrap();
+
+        TestValidator {
+            _dir: dir,
+            signer,
+            peer_id: PeerId::random(),
+            node: node.shared,
+        }
+    }
+
+    #[tokio::test]
+    async fn offline_leader_is_skipped() {
+        let signers = (1..=4).map(signer).collect::<Vec<_>>();
+        let rollup_contract = rollup_contract(&signers);
+
+        // Validators take turns in order, so the leader for height 1 is offline and the leader
+        // for height 2 builds on genesis instead
+        let validators = signers
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 1)
+            .map(|(_, signer)| validator(signer.clone(), rollup_contract.clone()))
+            .collect::<Vec<_>>();
+        let [other, leader, last] = &validators[..] else {
+            unreachable!()
+        };
+        assert_eq!(
+            leader.node.get_leader_for_block_height(BlockHeight(2)),
+            Some(leader.signer.address())
+        );
+
+        let genesis = Block::genesis();
+        for validator in [other, last] {
+            leader
+                .node
+                .receive_peer_binding(
+                    validator.peer_id,
+                    PeerBinding::new(&validator.signer, &validator.peer_id),
+                )
+                .unwrap();
+
+            // Sent by the other validators' tick workers once their timers run out
+            let skip = ApprovalContent::new(genesis.hash(), 0, 2).to_approval(&validator.signer);
+            leader
+                .node
+                .receive_accept(validator.peer_id, &skip)
+                .await
+                .unwrap();
+        }
+
+        // The leader's own skip is sent once its timer runs out too
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while leader.node.height() < BlockHeight(2) {
+            assert!(Instant::now() < deadline, "leader didn't produce a block");
+
+            leader.tick().await;
+            tokio::time::sleep(APPROVAL_CHECK_INTERVAL).await;
+        }
+
+        assert!(leader.node.get_block(BlockHeight(1)).unwrap().is_none());
+        let block = leader
+            .node
+            .get_block(BlockHeight(2))
+            .unwrap()
+            .unwrap()
+            .into_block();
+        assert_eq!(block.content.header.last_block_hash, genesis.hash());
+        assert_eq!(block.content.header.approvals.len(), 3);
+
+        // Two of four validators isn't more than 2/3 of the stake
+        let mut content = block.content.clone();
+        content.header.approvals.truncate(2);
+        let underapproved = content.to_block(&leader.signer);
+        assert!(matches!(
+            other.node.validate_block(&underapproved),
+            Err(Error::InvalidBlockApproval { .. })
+        ));
+
+        other.node.receive_proposal(block.clone()).unwrap();
+        other.tick().await;
+        assert_eq!(other.node.height(), BlockHeight(2));
+        assert_eq!(*other.node.block_cache.lock().hash(), block.hash());
+    }
+}
@@ -1,17 +1,54 @@
 use std::{sync::Arc, time::Duration};
 
 use ethereum_types::U64;
+use primitives::hash::CryptoHash;
+use serde::Serialize;
 use smirk::Element;
+use tokio::sync::oneshot;
 use tracing::{error, info, instrument};
 
+use super::rejected_txn_format::{RejectedTxn, RejectedTxnFormat};
 use crate::{
+    mempool::MintOrBurn,
+    metrics::metrics,
     network::NetworkEvent,
-    utxo::{validate_txn, UtxoProof},
+    types::BlockHeight,
+    utxo::{txn_failures, validate_txn, validate_txn_state, UtxoProof},
     Block, Error, NodeShared, Result,
 };
 
+/// The status of a txn submitted to this node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum TxnStatus {
+    /// The txn is in the mempool, waiting to be included in a block
+    Pending,
+    /// The txn was included in a block, but the block has not been rolled up yet
+    Included { height: BlockHeight },
+    /// The txn was removed from the mempool without being included in a block
+    Rejected { reason: String },
+    /// The txn was included in a block which has been rolled up to the rollup contract
+    RolledUp { height: BlockHeight },
+}
+
 impl NodeShared {
     pub async fn submit_transaction_and_wait(&self, utxo: UtxoProof) -> Result<Arc<Block>> {
+        self.submit_transaction(utxo)
+            .await?
+            .await
+            .expect("recv error")
+    }
+
+    /// Validate a txn, add it to the mempool and broadcast it to other nodes, without waiting
+    /// for it to be committed. The returned receiver resolves once the txn is committed or
+    /// rejected
+    ///
+    /// Mints and burns that aren't in the rollup contract yet are retried until they are, or
+    /// until `safe-eth-height-offset` eth blocks have passed, before this returns
+    pub async fn submit_transaction(
+        &self,
+        utxo: UtxoProof,
+    ) -> Result<oneshot::Receiver<Result<Arc<Block>>>> {
         let mut started_waiting_at_eth_block = None;
         loop {
             match self.validate_transaction(&utxo).await {
@@ -38,23 +75,104 @@ impl NodeShared {
                     // that are not even valid at `latest` height yet,
                     // because they are still in eth mempool
                     if self.config.safe_eth_height_offset == 0 || waited_too_long_for_confirmation {
+                        self.record_rejected_txn(utxo.hash(), &err);
                         return Err(err);
                     }
                 }
-                Err(err) => return Err(err),
+                Err(err) => {
+                    self.record_rejected_txn(utxo.hash(), &err);
+                    return Err(err);
+                }
             }
 
             tokio::time::sleep(Duration::from_secs(6)).await;
         }
 
-        self.send_all(NetworkEvent::Transaction(utxo.clone())).await;
+        // Add to our mempool before broadcasting, so txns conflicting with a pending txn are
+        // rejected immediately
+        let changes = mempool_changes(&utxo);
+        let (committed, evicted) = self
+            .mempool
+            .add_with_receiver(utxo.hash(), utxo.clone(), changes)
+            .map_err(|err| {
+                self.record_rejected_txn(utxo.hash(), &err);
+                err
+            })?;
+        if let Some(evicted) = evicted {
+            self.record_rejected_txn(evicted, &Error::MempoolFull);
+        }
+
+        self.send_all(NetworkEvent::Transaction(utxo)).await;
 
-        let changes = utxo.leaves();
-        self.mempool.add_wait(utxo.hash(), utxo, changes).await
+        Ok(committed)
     }
 
     pub(super) async fn validate_transaction(&self, utxo: &UtxoProof) -> Result<()> {
-        let is_mint_or_burn = utxo.mb_hash != Element::ZERO && utxo.mb_value != Element::ZERO;
+        self.validate_mint_or_burn(utxo).await?;
+
+        validate_txn(
+            self.config.mode,
+            utxo,
+            self.height(),
+            &self.block_store,
+            &self.notes_tree.read(),
+            &self.verified_proofs,
+        )
+    }
+
+    /// Same as [`Self::validate_transaction`], but skips the proof check, for txns whose proofs
+    /// have already been verified (e.g. with [`UtxoProof::verify_batch`])
+    pub(super) async fn validate_transaction_state(&self, utxo: &UtxoProof) -> Result<()> {
+        self.validate_mint_or_burn(utxo).await?;
+
+        validate_txn_state(
+            self.config.mode,
+            utxo,
+            self.height(),
+            &self.block_store,
+            &self.notes_tree.read(),
+        )
+    }
+
+    /// Check a txn as if it was submitted, without adding it to the mempool. Returns every
+    /// check that failed, including conflicts with txns in the mempool, so an empty list means
+    /// the txn would currently be accepted
+    pub(crate) async fn simulate_transaction(&self, utxo: &UtxoProof) -> Result<Vec<Error>> {
+        let mut failures = Vec::new();
+
+        match self.validate_mint_or_burn(utxo).await {
+            Ok(()) => {}
+            Err(
+                err @ (Error::MintIsNotInTheContract { .. }
+                | Error::BurnIsNotInTheContract { .. }
+                | Error::InvalidMintOrBurnLeaves),
+            ) => failures.push(err),
+            Err(err) => return Err(err),
+        }
+
+        failures.extend(txn_failures(
+            self.config.mode,
+            utxo,
+            self.height(),
+            &self.block_store,
+            &self.notes_tree.read(),
+            &self.verified_proofs,
+        )?);
+
+        failures.extend(
+            self.mempool
+                .conflicting_changes(&utxo.hash(), &mempool_changes(utxo))
+                .into_iter()
+                .map(|leaf| Error::LeafAlreadyInMempool { leaf }),
+        );
+
+        Ok(failures)
+    }
+
+    /// Check that a mint or burn txn is in the rollup contract at the safe eth height.
+    /// Other txns are always valid
+    async fn validate_mint_or_burn(&self, utxo: &UtxoProof) -> Result<()> {
+        let is_mint_or_burn = utxo.is_mint_or_burn();
         if is_mint_or_burn {
             let eth_block = self
                 .rollup_contract
@@ -100,13 +218,78 @@ impl NodeShared {
             }
         }
 
-        validate_txn(
-            self.config.mode,
-            utxo,
-            self.height(),
-            &self.block_store,
-            &self.notes_tree.read(),
-        )
+        Ok(())
+    }
+
+    /// Get the status of a txn, or `None` if this node has never seen the txn (or has forgotten
+    /// that it was rejected)
+    pub(crate) async fn txn_status(&self, txn_hash: CryptoHash) -> Result<Option<TxnStatus>> {
+        if self.mempool.contains(&txn_hash) {
+            return Ok(Some(TxnStatus::Pending));
+        }
+
+        if let Some((_, metadata)) = self.get_txn(txn_hash.into_inner())? {
+            let height = metadata.block_height;
+            let rolled_up_height = BlockHeight(self.rollup_contract.block_height().await?);
+
+            return Ok(Some(match height <= rolled_up_height {
+                true => TxnStatus::RolledUp { height },
+                false => TxnStatus::Included { height },
+            }));
+        }
+
+        Ok(self
+            .get_rejected_txn(txn_hash)?
+            .map(|rejected| TxnStatus::Rejected {
+                reason: rejected.reason,
+            }))
+    }
+
+    /// Remember that a txn failed validation, so the reason can be looked up later.
+    /// Only the most recent `rejected-txn-retention` rejections are kept
+    pub(crate) fn record_rejected_txn(&self, txn_hash: CryptoHash, err: &Error) {
+        metrics().record_txn_validation_failure(err);
+
+        let kind: &'static str = err.into();
+        let rejected = RejectedTxnFormat::V1(RejectedTxn {
+            kind: kind.to_string(),
+            reason: err.to_string(),
+            height: self.height(),
+            time: chrono::Utc::now().timestamp() as u64,
+        });
+
+        if let Err(err) = self.block_store.set_rejected_txn(
+            txn_hash.into_inner(),
+            &rejected,
+            self.config.rejected_txn_retention,
+        ) {
+            error!(?err, ?txn_hash, "Failed to record rejected txn");
+        }
+    }
+
+    /// Why a txn was rejected, if it is one of the last `rejected-txn-retention` txns to be
+    /// rejected
+    pub(crate) fn get_rejected_txn(&self, txn_hash: CryptoHash) -> Result<Option<RejectedTxn>> {
+        Ok(self
+            .block_store
+            .get_rejected_txn::<RejectedTxnFormat>(txn_hash.into_inner())?
+            .map(|RejectedTxnFormat::V1(rejected)| rejected))
+    }
+
+    /// Commit the results of leased txns to the mempool, recording why any failed txns were
+    /// rejected
+    pub(super) fn commit_to_mempool(
+        &self,
+        height: BlockHeight,
+        keys_with_results: Vec<(&CryptoHash, Result<Arc<Block>>)>,
+    ) {
+        for (txn_hash, result) in &keys_with_results {
+            if let Err(err) = result {
+                self.record_rejected_txn(**txn_hash, err);
+            }
+        }
+
+        self.mempool.commit(height, keys_with_results);
     }
 
     #[instrument(skip(self))]
@@ -114,6 +297,7 @@ impl NodeShared {
         info!("Received transaction");
 
         if let Err(err) = self.validate_transaction(&txn).await {
+            self.record_rejected_txn(txn.hash(), &err);
             error!(
                 ?err,
                 "Failed to validate transaction received from another node"
@@ -121,9 +305,25 @@ impl NodeShared {
             return Ok(());
         }
 
-        let changes = txn.leaves();
-        self.mempool.add(txn.hash(), txn, changes);
+        let changes = mempool_changes(&txn);
+        match self.mempool.add(txn.hash(), txn, changes) {
+            Ok(Some(evicted)) => self.record_rejected_txn(evicted, &Error::MempoolFull),
+            Ok(None) => {}
+            Err(err) => error!(
+                ?err,
+                "Failed to add transaction received from another node to mempool"
+            ),
+        }
 
         Ok(())
     }
 }
+
+/// The leaves a txn adds to the tree, any other pending txn with one of these leaves conflicts
+/// with it. Null leaves are padding, so they never conflict
+fn mempool_changes(utxo: &UtxoProof) -> Vec<Element> {
+    utxo.leaves()
+        .into_iter()
+        .filter(|leaf| *leaf != Element::ZERO)
+        .collect()
+}
@@ -0,0 +1,117 @@
+use block_store::{BlockListOrder, StoreList};
+use smirk::{Element, Path};
+
+use crate::{
+    constants::{MERKLE_TREE_DEPTH, RECENT_ROOT_COUNT},
+    types::BlockHeight,
+    Error, NodeShared, PersistentMerkleTree, Result,
+};
+
+/// A read-only view of the notes tree as it was at a given block height
+///
+/// Rather than keeping a copy of the tree for every height, the view "removes" the leaves
+/// inserted by every block after `height` from the current tree when calculating paths
+pub(crate) struct TreeSnapshot<'a> {
+    tree: &'a PersistentMerkleTree,
+    /// Leaves inserted after the height of the snapshot
+    removed: Vec<Element>,
+}
+
+impl<'a> TreeSnapshot<'a> {
+    pub(crate) fn root_hash(&self) -> Element {
+        self.tree.tree().root_hash_without(&self.removed)
+    }
+
+    pub(crate) fn contains_element(&self, element: &Element) -> bool {
+        self.tree.tree().contains_element(element) && !self.removed.contains(element)
+    }
+
+    pub(crate) fn path_for(&self, element: Element) -> Path<MERKLE_TREE_DEPTH> {
+        self.tree.tree().path_for_without(element, &self.removed)
+    }
+}
+
+impl NodeShared {
+    /// Create a view of `notes_tree` at `height`, which must be one of the last
+    /// [`RECENT_ROOT_COUNT`] blocks (i.e. a block whose root is accepted by `validate_txn`)
+    pub(crate) fn tree_snapshot<'a>(
+        &self,
+        notes_tree: &'a PersistentMerkleTree,
+        height: BlockHeight,
+    ) -> Result<TreeSnapshot<'a>> {
+        let next_block = self.height().next();
+        let min_height = BlockHeight(next_block.saturating_sub(RECENT_ROOT_COUNT));
+
+        if height < min_height || height >= next_block {
+            return Err(Error::HeightNotRecentEnough {
+                height,
+                min_height,
+                max_height: BlockHeight(next_block.0 - 1),
+            });
+        }
+
+        let block = self
+            .get_block(height)?
+            .ok_or(Error::BlockNotFound { block: height })?
+            .into_block();
+
+        // Blocks may have been committed to the block store but not yet applied to the tree, but
+        // removing leaves that aren't in the tree is a no-op, so we don't need to special case it
+        let removed = self
+            .block_store
+            .list_non_empty(height.next().., BlockListOrder::LowestToHighest)
+            .into_iterator()
+            .map(|r| Ok::<_, Error>(r?.1.into_block()))
+            .map(|b| {
+                Ok::<_, Error>(
+                    b?.content
+                        .state
+                        .txns
+                        .iter()
+                        .flat_map(|txn| txn.leaves())
+                        .filter(|e| *e != Element::ZERO)
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let snapshot = TreeSnapshot {
+            tree: notes_tree,
+            removed,
+        };
+
+        let root_hash = snapshot.root_hash();
+        if root_hash != block.content.state.root_hash {
+            return Err(Error::InvalidBlockRoot {
+                got: root_hash,
+                expected: block.content.state.root_hash,
+            });
+        }
+
+        Ok(snapshot)
+    }
+
+    pub(crate) fn get_merkle_paths_at(
+        &self,
+        elements: &[Element],
+        height: BlockHeight,
+    ) -> Result<Vec<Vec<Element>>> {
+        let notes_tree = self.notes_tree.read();
+        let snapshot = self.tree_snapshot(&notes_tree, height)?;
+
+        elements
+            .iter()
+            .map(|e| {
+                // Check the element was in the tree at `height`
+                if !snapshot.contains_element(e) {
+                    return Err(Error::ElementNotInTree { element: *e });
+                }
+
+                Ok(snapshot.path_for(*e).siblings_deepest_first().to_vec())
+            })
+            .collect::<Result<Vec<Vec<Element>>>>()
+    }
+}
@@ -2,6 +2,7 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use primitives::block_height::BlockHeight;
 use serde::{Deserialize, Serialize};
 use wire_message::WireMessage;
+use zk_primitives::Element;
 
 use crate::utxo::UtxoProof;
 
@@ -44,4 +45,15 @@ impl block_store::Transaction for TxnFormat {
             Self::V1(txn, _) => txn.hash().into_inner(),
         }
     }
+
+    fn leaves(&self) -> Vec<[u8; 32]> {
+        match self {
+            Self::V1(txn, _) => txn
+                .leaves()
+                .into_iter()
+                .filter(|leaf| *leaf != Element::ZERO)
+                .map(|leaf| leaf.to_be_bytes())
+                .collect(),
+        }
+    }
 }
@@ -7,6 +7,7 @@ use std::time::Duration;
 use super::{Error, Result};
 use crate::config::Config;
 use crate::constants::MERKLE_TREE_DEPTH;
+use crate::metrics::metrics;
 use crate::prover::db::{LastSeenBlock, ProverDb};
 use crate::types::BlockHeight;
 use crate::{Mode, NodeShared, PersistentMerkleTree};
@@ -215,6 +216,7 @@ where
     while let Some(commit) = stream.next().await {
         let commit = commit?;
         let commit_height = commit.content.header.height;
+        metrics().set_prover_height(commit_height);
         if commit.content.state.txns.is_empty() {
             prover_state_db.set_last_seen_block(LastSeenBlock {
                 height: commit_height,
@@ -534,18 +536,37 @@ async fn run_rollup_worker(
 
         info!(counter.rolling_up_height = ?height, "Rolling up proof");
 
-        if let Err(err) = prover.rollup(&rollup).await {
-            if let prover::Error::RollupTransactionTimeout = err {
-                // This should exit the process
-                return Err(err.into());
-            }
+        let tx = match prover.rollup(&rollup).await {
+            Ok(tx) => tx,
+            Err(err) => {
+                if let prover::Error::RollupTransactionTimeout = err {
+                    // This should exit the process
+                    return Err(err.into());
+                }
 
-            error!(?err, ?rollup, "Failed to roll up proof");
-            continue;
-        }
+                error!(?err, ?rollup, "Failed to roll up proof");
+                continue;
+            }
+        };
 
         info!(counter.rolled_up_height = ?height, "Rolled up proof");
 
+        match rollup_contract
+            .client
+            .client()
+            .eth()
+            .transaction_receipt(tx)
+            .await
+        {
+            Ok(Some(receipt)) => {
+                if let Some(gas_used) = receipt.gas_used {
+                    metrics().record_rollup_gas(gas_used.low_u64());
+                }
+            }
+            Ok(None) => {}
+            Err(err) => error!(?err, ?tx, "Failed to get rollup transaction receipt"),
+        }
+
         if let Some(rollup_subscription) = &rollup_subscription {
             rollup_subscription.send(height).await?;
         }
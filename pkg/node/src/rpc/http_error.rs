@@ -1,6 +1,6 @@
 use super::routes;
 use crate::errors;
-use primitives::hash::CryptoHash;
+use primitives::{block_height::BlockHeight, hash::CryptoHash};
 use rpc::{code::ErrorCode, error::HTTPError};
 use serde::Serialize;
 use zk_primitives::Element;
@@ -46,6 +46,11 @@ pub struct HashData {
     pub hash: CryptoHash,
 }
 
+#[derive(Debug, Serialize)]
+pub struct HeightData {
+    pub height: BlockHeight,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ElementStringData {
     pub element: String,
@@ -73,12 +78,24 @@ impl From<errors::Error> for HTTPError {
                     }),
                 )
             }
+            errors::Error::HeightNotRecentEnough { height, .. } => HTTPError::new(
+                ErrorCode::OutOfRange,
+                "height-not-recent-enough",
+                Some(err.into()),
+                Some(HeightData { height }),
+            ),
             errors::Error::ElementNotInTree { element } => HTTPError::new(
                 ErrorCode::NotFound,
                 "element-not-found",
                 Some(err.into()),
                 Some(ElementData { element }),
             ),
+            errors::Error::ElementInTree { element } => HTTPError::new(
+                ErrorCode::AlreadyExists,
+                "element-in-tree",
+                Some(err.into()),
+                Some(ElementData { element }),
+            ),
             errors::Error::NoteAlreadySpent {
                 spent_note: nullifier,
                 ..
@@ -122,6 +139,18 @@ impl From<errors::Error> for HTTPError {
                 Some(err.into()),
                 Some(ElementData { element }),
             ),
+            errors::Error::LeafAlreadyInMempool { leaf } => HTTPError::new(
+                ErrorCode::AlreadyExists,
+                "mempool-conflict",
+                Some(err.into()),
+                Some(ElementData { element: leaf }),
+            ),
+            errors::Error::MempoolFull => HTTPError::new(
+                ErrorCode::ResourceExhausted,
+                "mempool-full",
+                Some(err.into()),
+                None::<()>,
+            ),
             errors::Error::TxnNotFound { txn } => HTTPError::new(
                 ErrorCode::NotFound,
                 "txn-not-found",
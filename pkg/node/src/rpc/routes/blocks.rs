@@ -141,11 +141,7 @@ pub async fn get_block(
             block.content.header.height,
             max_height,
         ));
-    Ok(web::Json(BlockResponse {
-        time,
-        hash: block.hash(),
-        block: Block::from_node_block(block, time),
-    }))
+    Ok(web::Json(BlockResponse::new(block, time)))
 }
 
 #[derive(Serialize)]
@@ -155,6 +151,16 @@ pub struct BlockWithInfo {
     time: u64,
 }
 
+impl BlockWithInfo {
+    pub(crate) fn new(block: crate::block::Block, time: u64) -> Self {
+        Self {
+            time,
+            hash: block.hash(),
+            block: Block::from_node_block(block, time),
+        }
+    }
+}
+
 #[derive(Serialize)]
 pub struct ListBlocksResponse {
     blocks: Vec<BlockWithInfo>,
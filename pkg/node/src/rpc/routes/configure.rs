@@ -1,23 +1,36 @@
-use super::{blocks, element, health, height, merkle, stats, txn, State};
+use super::{
+    blocks, element, health, height, mempool, merkle, stats, status, subscribe, txn, State,
+};
 use actix_web::web;
 
 pub fn configure_routes(state: State) -> Box<dyn FnOnce(&mut web::ServiceConfig)> {
     Box::new(move |cfg: &mut web::ServiceConfig| {
         cfg.app_data(web::Data::new(state))
             .service(web::resource("/health").get(health::get_health))
+            .service(web::resource("/health/ready").get(health::get_health))
+            .service(web::resource("/health/live").get(health::get_live))
+            .service(web::resource("/status").get(status::get_status))
             .service(web::resource("/height").get(height::get_height))
+            .service(web::resource("/mempool").get(mempool::get_mempool))
             .service(web::resource("/merkle").get(merkle::get_merkle_paths))
+            .service(web::resource("/merkle/absent").get(merkle::get_merkle_absence_proofs))
+            .service(
+                web::resource("/elements/{element}/transaction").get(txn::get_txn_by_element),
+            )
             .service(web::resource("/elements/{element}").get(element::get_element))
             .service(web::resource("/elements").get(element::list_elements))
             .service(web::resource("/blocks/{block}").get(blocks::get_block))
             .service(web::resource("/blocks").get(blocks::list_blocks))
             .service(web::resource("/transaction").post(txn::submit_txn))
+            .service(web::resource("/transactions/simulate").post(txn::simulate_txn))
             .service(web::resource("/transactions/{hash}").get(txn::get_txn))
+            .service(web::resource("/transactions/{hash}/status").get(txn::get_txn_status))
             .service(
                 web::resource("/transactions")
                     .get(txn::list_txns)
                     .post(txn::submit_txn),
             )
-            .service(web::resource("/stats").get(stats::get_stats));
+            .service(web::resource("/stats").get(stats::get_stats))
+            .service(web::resource("/subscribe").get(subscribe::subscribe));
     })
 }
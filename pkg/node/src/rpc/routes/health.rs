@@ -1,4 +1,5 @@
 use super::{error, State};
+use crate::config::HistoryMode;
 use actix_web::web;
 use rpc::error::HttpResult;
 use serde::Serialize;
@@ -6,9 +7,26 @@ use serde::Serialize;
 #[derive(Serialize)]
 pub struct HealthResp {
     height: u64,
+    history_mode: HistoryMode,
+    /// Blocks below this height have been pruned, and can't be fetched from this node
+    pruned_below: Option<u64>,
 }
 
-/// GET /health - returns data about the rollup (e.g. root hash, version, etc)
+#[derive(Serialize)]
+pub struct LiveResp {
+    height: u64,
+}
+
+/// GET /health/live - succeeds as long as the node is running and serving requests, even if
+/// it is out of sync. Use /health/ready to check if the node can serve up to date data
+#[tracing::instrument(skip(state))]
+pub async fn get_live(state: web::Data<State>) -> HttpResult<web::Json<LiveResp>> {
+    Ok(web::Json(LiveResp {
+        height: state.node.height().0,
+    }))
+}
+
+/// GET /health, GET /health/ready - returns data about the rollup (e.g. root hash, version, etc)
 /// unlike /height, /health will return an error if the node is unhealthy (i.e.
 /// out of sync with other nodes)
 #[tracing::instrument(skip(state))]
@@ -26,5 +44,7 @@ pub async fn get_health(state: web::Data<State>) -> HttpResult<web::Json<HealthR
 
     Ok(web::Json(HealthResp {
         height: state.node.height().0,
+        history_mode: state.node.history_mode(),
+        pruned_below: state.node.pruned_height()?.map(|h| h.0),
     }))
 }
@@ -0,0 +1,36 @@
+use super::State;
+use actix_web::web;
+use primitives::hash::CryptoHash;
+use rpc::error::HttpResult;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct MempoolTxn {
+    hash: CryptoHash,
+    /// Milliseconds since the txn was added to the mempool
+    age_ms: u128,
+    /// Whether the txn is currently part of a block proposal
+    leased: bool,
+}
+
+#[derive(Serialize)]
+pub struct MempoolResponse {
+    txns: Vec<MempoolTxn>,
+}
+
+/// GET /mempool - returns the txns waiting to be included in a block
+#[tracing::instrument(err, skip(state))]
+pub async fn get_mempool(state: web::Data<State>) -> HttpResult<web::Json<MempoolResponse>> {
+    let txns = state
+        .node
+        .mempool_txns()
+        .into_iter()
+        .map(|entry| MempoolTxn {
+            hash: entry.key,
+            age_ms: entry.age.as_millis(),
+            leased: entry.leased,
+        })
+        .collect();
+
+    Ok(web::Json(MempoolResponse { txns }))
+}
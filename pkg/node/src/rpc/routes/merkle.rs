@@ -1,5 +1,6 @@
 use super::{error, State};
 use actix_web::web;
+use primitives::block_height::BlockHeight;
 use rpc::error::HttpResult;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
@@ -8,6 +9,8 @@ use zk_primitives::Element;
 #[derive(Debug, Deserialize)]
 pub struct MerklePathRequestQuery {
     commitments: String,
+    /// Generate paths against the root of this block, rather than the current root
+    height: Option<u64>,
 }
 
 #[derive(Serialize)]
@@ -33,6 +36,70 @@ pub async fn get_merkle_paths(
         })
         .collect::<HttpResult<Vec<Element>>>()?;
 
-    let paths = state.node.get_merkle_paths(&commitments)?;
+    let paths = match query.height {
+        Some(height) => state
+            .node
+            .get_merkle_paths_at(&commitments, BlockHeight(height))?,
+        None => state.node.get_merkle_paths(&commitments)?,
+    };
     Ok(web::Json(MerklePathResponse { paths }))
 }
+
+#[derive(Debug, Deserialize)]
+pub struct MerkleAbsenceRequestQuery {
+    elements: String,
+}
+
+#[derive(Serialize)]
+pub struct MerkleAbsenceProof {
+    /// The element that is absent from the tree
+    element: Element,
+    /// The element currently occupying the slot of `element`, if any
+    occupant: Option<Element>,
+    siblings: Vec<Element>,
+    root_hash: Element,
+}
+
+#[derive(Serialize)]
+pub struct MerkleAbsenceResponse {
+    proofs: Vec<MerkleAbsenceProof>,
+}
+
+/// GET /merkle/absent - returns proofs that the given elements are not in the tree (e.g. that a
+/// nullifier is unspent)
+#[tracing::instrument(err, skip_all)]
+pub async fn get_merkle_absence_proofs(
+    state: web::Data<State>,
+    query: web::Query<MerkleAbsenceRequestQuery>,
+) -> HttpResult<web::Json<MerkleAbsenceResponse>> {
+    tracing::info!(
+        method = "get_merkle_absence_proofs",
+        ?query,
+        "Incoming request"
+    );
+
+    let elements = query
+        .0
+        .elements
+        .split(',')
+        .map(|c| {
+            Element::from_str(c)
+                .map_err(|e| error::Error::InvalidElement(c.to_string(), e))
+                .map_err(rpc::error::HTTPError::from)
+        })
+        .collect::<HttpResult<Vec<Element>>>()?;
+
+    let proofs = state
+        .node
+        .get_exclusion_proofs(&elements)?
+        .into_iter()
+        .map(|proof| MerkleAbsenceProof {
+            element: proof.element(),
+            occupant: proof.occupant(),
+            siblings: proof.path().siblings_deepest_first().to_vec(),
+            root_hash: proof.actual_root_hash(),
+        })
+        .collect();
+
+    Ok(web::Json(MerkleAbsenceResponse { proofs }))
+}
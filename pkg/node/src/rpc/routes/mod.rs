@@ -4,9 +4,12 @@ pub mod element;
 pub mod error;
 pub mod health;
 pub mod height;
+pub mod mempool;
 pub mod merkle;
 pub mod state;
 pub mod stats;
+pub mod status;
+pub mod subscribe;
 pub mod txn;
 
 pub use configure::configure_routes;
@@ -0,0 +1,90 @@
+use super::State;
+use crate::{sync::SyncStatus, Mode};
+use actix_web::web;
+use contracts::H256;
+use primitives::{hash::CryptoHash, peer::Address};
+use rpc::error::HttpResult;
+use serde::Serialize;
+use std::{collections::HashMap, time::Duration};
+
+/// How long to wait for the rollup contract, so a slow RPC provider doesn't stall the status
+const ROLLED_UP_HEIGHT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize)]
+pub struct DoomslugTip {
+    hash: CryptoHash,
+    height: u64,
+    /// Largest height of a block endorsed by more than 2/3 of the validators
+    final_height: u64,
+}
+
+#[derive(Serialize)]
+pub struct StatusResp {
+    mode: Mode,
+    /// Height of the last committed block
+    height: u64,
+    /// Highest block height seen from other nodes
+    max_seen_height: u64,
+    out_of_sync: bool,
+    sync_status: SyncStatus,
+    peers: Vec<String>,
+    /// Addresses of the connected peers that have bound their peer id to an address
+    peer_addresses: HashMap<String, Address>,
+    /// Number of txns in the mempool, both pending and included in a proposal
+    mempool_size: usize,
+    /// Milliseconds since the last block was committed
+    last_commit_age_ms: Option<u128>,
+    /// Height of the last block rolled up to the rollup contract, if the contract could be reached
+    /// in time
+    rolled_up_height: Option<u64>,
+    /// Number of elements in the notes tree
+    notes_tree_size: usize,
+    /// Hash of the validator stakes, which must be the same on every validator
+    validator_stakes_hash: H256,
+    doomslug_tip: DoomslugTip,
+}
+
+/// GET /status - returns detailed data about the node's state, for debugging
+#[tracing::instrument(err, skip(state))]
+pub async fn get_status(state: web::Data<State>) -> HttpResult<web::Json<StatusResp>> {
+    let node = &state.node;
+
+    let rolled_up_height =
+        match tokio::time::timeout(ROLLED_UP_HEIGHT_TIMEOUT, node.rolled_up_height()).await {
+            Ok(Ok(height)) => Some(height.0),
+            Ok(Err(err)) => {
+                tracing::warn!(?err, "Failed to get rolled up height");
+                None
+            }
+            Err(_) => {
+                tracing::warn!("Timed out getting rolled up height");
+                None
+            }
+        };
+
+    let (tip_hash, tip_height) = node.doomslug_tip();
+    let peers = node.connected_peers();
+
+    Ok(web::Json(StatusResp {
+        mode: node.mode(),
+        height: node.height().0,
+        max_seen_height: node.max_height().0,
+        out_of_sync: node.is_out_of_sync(),
+        sync_status: node.sync_status(),
+        peers: peers.iter().map(ToString::to_string).collect(),
+        peer_addresses: peers
+            .iter()
+            .filter_map(|peer| Some((peer.to_string(), node.peer_address(peer)?)))
+            .collect(),
+        mempool_size: node.mempool_len(),
+        last_commit_age_ms: node.last_commit_time().map(|t| t.elapsed().as_millis()),
+        rolled_up_height,
+        notes_tree_size: node.notes_tree_len(),
+        validator_stakes_hash: node.validator_stakes_hash(),
+        doomslug_tip: DoomslugTip {
+            hash: tip_hash,
+            height: tip_height,
+            final_height: node.doomslug_final_height().0,
+        },
+    }))
+}
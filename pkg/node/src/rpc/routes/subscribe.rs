@@ -0,0 +1,146 @@
+use std::{collections::HashSet, convert::Infallible, str::FromStr, time::Duration};
+
+use super::{blocks::BlockWithInfo, error, txn::TxnWithInfo, State};
+use crate::block::Block;
+use actix_web::{web, web::Bytes, HttpResponse};
+use futures::StreamExt;
+use rpc::error::HttpResult;
+use serde::{Deserialize, Serialize};
+use tokio_stream::wrappers::{IntervalStream, UnboundedReceiverStream};
+use zk_primitives::Element;
+
+/// How often to send a comment to keep idle connections open
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Deserialize)]
+pub struct SubscribeQuery {
+    /// Comma separated output commitments, if set only txns creating one of these
+    /// commitments are sent
+    commitments: Option<String>,
+}
+
+/// An event sent to subscribers when a block is committed
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Event {
+    Block(BlockWithInfo),
+    Txn(TxnWithInfo),
+}
+
+impl Event {
+    fn name(&self) -> &'static str {
+        match self {
+            Event::Block(_) => "block",
+            Event::Txn(_) => "txn",
+        }
+    }
+
+    fn to_sse(&self) -> Bytes {
+        #[allow(clippy::unwrap_used)]
+        let data = serde_json::to_string(self).unwrap();
+        Bytes::from(format!("event: {}\ndata: {data}\n\n", self.name()))
+    }
+}
+
+/// The events for a committed block. Without a commitments filter this is the block followed
+/// by each of its txns, otherwise only the txns with a matching output commitment
+fn block_events(block: &Block, commitments: Option<&HashSet<Element>>, time: u64) -> Vec<Event> {
+    let height = block.content.header.height;
+
+    let txns = block
+        .content
+        .state
+        .txns
+        .iter()
+        .enumerate()
+        .filter(|(_, txn)| match commitments {
+            Some(commitments) => txn.output_leaves.iter().any(|c| commitments.contains(c)),
+            None => true,
+        })
+        .map(|(index_in_block, txn)| {
+            Event::Txn(TxnWithInfo {
+                hash: txn.hash(),
+                proof: txn.clone(),
+                index_in_block: index_in_block as u64,
+                block_height: height,
+                time,
+            })
+        });
+
+    match commitments {
+        Some(_) => txns.collect(),
+        None => std::iter::once(Event::Block(BlockWithInfo::new(block.clone(), time)))
+            .chain(txns)
+            .collect(),
+    }
+}
+
+/// GET /subscribe - a Server-Sent Events stream of committed blocks and txns
+#[tracing::instrument(err, skip(state))]
+pub async fn subscribe(
+    state: web::Data<State>,
+    web::Query(query): web::Query<SubscribeQuery>,
+) -> HttpResult<HttpResponse> {
+    let commitments = query
+        .commitments
+        .as_deref()
+        .map(|commitments| {
+            commitments
+                .split(',')
+                .map(|c| {
+                    Element::from_str(c)
+                        .map_err(|e| error::Error::InvalidElement(c.to_string(), e))
+                        .map_err(rpc::error::HTTPError::from)
+                })
+                .collect::<HttpResult<HashSet<Element>>>()
+        })
+        .transpose()?;
+
+    let events = UnboundedReceiverStream::new(state.node.commit_listener()).flat_map(move |block| {
+        let time = chrono::Utc::now().timestamp() as u64;
+        let events = block_events(&block, commitments.as_ref(), time);
+        futures::stream::iter(events.into_iter().map(|event| event.to_sse()))
+    });
+
+    let keep_alive = IntervalStream::new(tokio::time::interval(KEEP_ALIVE_INTERVAL))
+        .map(|_| Bytes::from_static(b": keep-alive\n\n"));
+
+    let stream = futures::stream::select(events, keep_alive).map(Ok::<_, Infallible>);
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UtxoProof;
+
+    #[test]
+    fn block_events_filter_by_commitment() {
+        let new_proof = |output: u64| UtxoProof {
+            output_leaves: [Element::new(output), Element::ZERO],
+            ..UtxoProof::default()
+        };
+
+        let mut block = Block::default();
+        block.content.state.txns = vec![new_proof(1), new_proof(2), new_proof(3)];
+
+        let events = block_events(&block, None, 0);
+        let names = events.iter().map(Event::name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["block", "txn", "txn", "txn"]);
+
+        let commitments = HashSet::from([Element::new(2), Element::new(3)]);
+        let events = block_events(&block, Some(&commitments), 0);
+        let indexes = events
+            .iter()
+            .map(|event| match event {
+                Event::Txn(txn) => txn.index_in_block,
+                Event::Block(_) => panic!("unexpected block event"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(indexes, vec![1, 2]);
+    }
+}
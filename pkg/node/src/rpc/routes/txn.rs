@@ -1,7 +1,7 @@
 use std::{str::FromStr, sync::Arc};
 
 use super::State;
-use crate::{node, utxo::UtxoProof, BlockFormat};
+use crate::{node, utxo::UtxoProof, BlockFormat, RejectedTxn, TxnStatus};
 use actix_web::web;
 use base64::Engine;
 use block_store::BlockListOrder;
@@ -13,7 +13,7 @@ use primitives::{
     hash::CryptoHash,
     pagination::{Cursor, CursorChoice, OpaqueCursor, OpaqueCursorChoice, Paginator},
 };
-use rpc::error::{HTTPError, HttpResult};
+use rpc::error::{ErrorDetail, HTTPError, HttpResult};
 use serde::{Deserialize, Serialize};
 use wire_message::WireMessage;
 use zk_circuits::data::SnarkWitness;
@@ -31,17 +31,45 @@ pub struct SubmitUtxoResp {
     txn_hash: CryptoHash,
 }
 
+#[derive(Serialize)]
+pub struct SubmitUtxoPendingResp {
+    txn_hash: CryptoHash,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum SubmitTxnResp {
+    Committed(SubmitUtxoResp),
+    Pending(SubmitUtxoPendingResp),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubmitTxnQuery {
+    /// If false, return as soon as the txn is in the mempool instead of waiting for it to be
+    /// committed. Mints and burns that aren't in the rollup contract yet still wait for the
+    /// eth block to be confirmed (up to `safe-eth-height-offset` blocks) before they are added
+    /// to the mempool
+    #[serde(default = "default_wait")]
+    wait: bool,
+}
+
+fn default_wait() -> bool {
+    true
+}
+
 #[tracing::instrument(err, skip_all)]
 pub async fn submit_txn(
     state: web::Data<State>,
+    web::Query(query): web::Query<SubmitTxnQuery>,
     web::Json(data): web::Json<SubmitUtxoBody>,
-) -> HttpResult<web::Json<SubmitUtxoResp>> {
+) -> HttpResult<web::Json<SubmitTxnResp>> {
     let SnarkWitness::V1(snark) = &data.snark;
 
     tracing::info!(
         method = "submit_txn",
         instances = ?snark.instances,
         proof = base64::prelude::BASE64_STANDARD.encode(&snark.proof),
+        wait = query.wait,
         "Incoming request"
     );
 
@@ -49,14 +77,60 @@ pub async fn submit_txn(
     let utxo_hash = utxo.hash();
 
     let node = Arc::clone(&state.node);
+
+    if !query.wait {
+        tokio::spawn(async move { node.submit_transaction(utxo).await })
+            .await
+            .context("tokio spawn join handle error")??;
+
+        return Ok(web::Json(SubmitTxnResp::Pending(SubmitUtxoPendingResp {
+            txn_hash: utxo_hash,
+        })));
+    }
+
     let block = tokio::spawn(async move { node.submit_transaction_and_wait(utxo).await })
         .await
         .context("tokio spawn join handle error")??;
 
-    Ok(web::Json(SubmitUtxoResp {
+    Ok(web::Json(SubmitTxnResp::Committed(SubmitUtxoResp {
         height: block.content.header.height,
         root_hash: block.content.state.root_hash,
         txn_hash: utxo_hash,
+    })))
+}
+
+#[derive(Serialize)]
+pub struct SimulateTxnResp {
+    txn_hash: CryptoHash,
+    /// Whether the txn would currently be accepted, i.e. `failures` is empty
+    valid: bool,
+    /// Every check that failed, in the same format as an error response
+    failures: Vec<ErrorDetail>,
+}
+
+/// POST /transactions/simulate - check a txn without submitting it
+#[tracing::instrument(err, skip_all)]
+pub async fn simulate_txn(
+    state: web::Data<State>,
+    web::Json(data): web::Json<SubmitUtxoBody>,
+) -> HttpResult<web::Json<SimulateTxnResp>> {
+    tracing::info!(method = "simulate_txn", "Incoming request");
+
+    let utxo = UtxoProof::from_snark_witness(data.snark);
+    let txn_hash = utxo.hash();
+
+    let node = Arc::clone(&state.node);
+    let failures = tokio::spawn(async move { node.simulate_transaction(&utxo).await })
+        .await
+        .context("tokio spawn join handle error")??;
+
+    Ok(web::Json(SimulateTxnResp {
+        txn_hash,
+        valid: failures.is_empty(),
+        failures: failures
+            .into_iter()
+            .map(|err| HTTPError::from(err).detail())
+            .collect(),
     }))
 }
 
@@ -281,11 +355,25 @@ pub struct GetTxnResponse {
     txn: TxnWithInfo,
 }
 
+#[derive(Serialize)]
+pub struct GetRejectedTxnResponse {
+    hash: CryptoHash,
+    rejected: RejectedTxn,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum GetTxnOrRejectedResponse {
+    Txn(GetTxnResponse),
+    Rejected(GetRejectedTxnResponse),
+}
+
+/// GET /transactions/{hash} - a committed txn, or why the txn was rejected
 #[tracing::instrument(err, skip_all)]
 pub async fn get_txn(
     state: web::Data<State>,
     path: web::Path<(String,)>,
-) -> HttpResult<web::Json<GetTxnResponse>> {
+) -> HttpResult<web::Json<GetTxnOrRejectedResponse>> {
     tracing::info!(method = "get_txn", ?path, "Incoming request");
 
     let (txn_hash,) = path.into_inner();
@@ -295,10 +383,49 @@ pub async fn get_txn(
             source: err,
         })?;
 
+    let Some((txn, metadata)) = state.node.get_txn(txn_hash.into_inner())? else {
+        let rejected = state
+            .node
+            .get_rejected_txn(txn_hash)?
+            .ok_or(crate::Error::TxnNotFound { txn: txn_hash })?;
+
+        return Ok(web::Json(GetTxnOrRejectedResponse::Rejected(
+            GetRejectedTxnResponse {
+                hash: txn_hash,
+                rejected,
+            },
+        )));
+    };
+
+    let time = metadata.block_time.unwrap_or_else(|| {
+        node::NodeShared::estimate_block_time(metadata.block_height, state.node.max_height())
+    });
+
+    Ok(web::Json(GetTxnOrRejectedResponse::Txn(GetTxnResponse {
+        txn: TxnWithInfo {
+            proof: txn,
+            index_in_block: metadata.block_txn_index as u64,
+            hash: txn_hash,
+            block_height: metadata.block_height,
+            time,
+        },
+    })))
+}
+
+/// GET /elements/{element}/transaction - the txn that created or spent an element
+#[tracing::instrument(err, skip_all)]
+pub async fn get_txn_by_element(
+    state: web::Data<State>,
+    path: web::Path<(Element,)>,
+) -> HttpResult<web::Json<GetTxnResponse>> {
+    tracing::info!(method = "get_txn_by_element", ?path, "Incoming request");
+
+    let (element,) = path.into_inner();
+
     let (txn, metadata) = state
         .node
-        .get_txn(txn_hash.into_inner())?
-        .ok_or(crate::Error::TxnNotFound { txn: txn_hash })?;
+        .get_txn_by_leaf(element)?
+        .ok_or(crate::Error::ElementNotInTree { element })?;
 
     let time = metadata.block_time.unwrap_or_else(|| {
         node::NodeShared::estimate_block_time(metadata.block_height, state.node.max_height())
@@ -306,15 +433,38 @@ pub async fn get_txn(
 
     Ok(web::Json(GetTxnResponse {
         txn: TxnWithInfo {
+            hash: txn.hash(),
             proof: txn,
             index_in_block: metadata.block_txn_index as u64,
-            hash: txn_hash,
             block_height: metadata.block_height,
             time,
         },
     }))
 }
 
+#[tracing::instrument(err, skip_all)]
+pub async fn get_txn_status(
+    state: web::Data<State>,
+    path: web::Path<(String,)>,
+) -> HttpResult<web::Json<TxnStatus>> {
+    tracing::info!(method = "get_txn_status", ?path, "Incoming request");
+
+    let (txn_hash,) = path.into_inner();
+    let txn_hash =
+        CryptoHash::from_str(&txn_hash).map_err(|err| crate::Error::FailedToParseHash {
+            hash: txn_hash,
+            source: err,
+        })?;
+
+    let status = state
+        .node
+        .txn_status(txn_hash)
+        .await?
+        .ok_or(crate::Error::TxnNotFound { txn: txn_hash })?;
+
+    Ok(web::Json(status))
+}
+
 #[cfg(test)]
 mod tests {
     use primitives::pagination::Opaque;
@@ -12,6 +12,9 @@ use std::sync::Arc;
 
 use super::routes::{configure_routes, State};
 
+/// Paths that are served even while the node is out of sync
+const ALWAYS_AVAILABLE_PATHS: &[&str] = &["/metrics", "/v0/health/live", "/v0/status"];
+
 async fn root() -> impl Responder {
     HttpResponse::Ok()
         .content_type("application/json")
@@ -44,8 +47,9 @@ pub fn create_rpc_server(
                 let node = Arc::clone(&node);
 
                 move |req, srv| {
+                    let is_out_of_sync =
+                        node.is_out_of_sync() && !ALWAYS_AVAILABLE_PATHS.contains(&req.path());
                     let fut = srv.call(req);
-                    let is_out_of_sync = node.is_out_of_sync();
 
                     async move {
                         if is_out_of_sync {
@@ -58,6 +62,7 @@ pub fn create_rpc_server(
                 }
             })
             .service(web::resource("/").get(root))
+            .service(web::resource("/metrics").get(rpc::metrics::metrics))
             .service(web::scope("/v0").configure(configure_routes(state)))
             .default_service(web::route().to(not_found_error_handler))
     })
@@ -10,12 +10,16 @@ use std::{collections::HashSet, sync::Arc};
 use block_store::{BlockListOrder, StoreList};
 use contracts::RollupContract;
 use libp2p::PeerId;
+use p2p2::Misbehaviour;
 use parking_lot::Mutex;
 use prover::smirk_metadata::SmirkMetadata;
+use serde::Serialize;
 use tokio::sync::mpsc;
 use tracing::{error, info, warn};
+use zk_primitives::Element;
 
 use crate::{
+    block::Block,
     cache::BlockCache,
     network::{
         NetworkEvent, SnapshotAccept, SnapshotChunk, SnapshotChunkFast, SnapshotChunkSlow,
@@ -25,6 +29,8 @@ use crate::{
     NodeShared,
 };
 
+mod download;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("channel was closed")]
@@ -46,9 +52,25 @@ pub enum Error {
     TokioJoin(#[from] tokio::task::JoinError),
 }
 
+/// What the sync worker is currently doing
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyncStatus {
+    /// In sync, or waiting to retry
+    #[default]
+    Idle,
+    /// Sent a snapshot request, waiting for a peer to offer a snapshot
+    AwaitingOffer,
+    /// Accepted a snapshot offer, waiting for chunks
+    AwaitingChunk,
+    /// Applying received blocks or elements
+    ApplyingChunk,
+}
+
 pub enum Message {
     OutOfSync(OutOfSync),
     SnapshotOffer(SnapshotOffer),
+    SnapshotRefusal(SnapshotRefusal),
     SnapshotChunk(PeerId, SnapshotChunk),
 }
 
@@ -64,6 +86,13 @@ pub struct SnapshotOffer {
     pub snapshot_id: SnapshotId,
 }
 
+/// Same as [crate::network::NetworkEvent::SnapshotRefusal]
+pub struct SnapshotRefusal {
+    pub peer: PeerId,
+    pub snapshot_id: SnapshotId,
+    pub earliest_height: BlockHeight,
+}
+
 /// A channel for sending messages to the sync worker.
 #[derive(Clone)]
 pub struct SyncWorkerChannel(pub mpsc::UnboundedSender<Message>);
@@ -85,6 +114,22 @@ impl SyncWorkerChannel {
             .map_err(|_| Error::ChannelWasClosed)
     }
 
+    /// Handled by [SyncWorker::wait_for_snapshot_offer].
+    pub fn snapshot_refusal(
+        &self,
+        peer: PeerId,
+        snapshot_id: SnapshotId,
+        earliest_height: BlockHeight,
+    ) -> Result<(), Error> {
+        self.0
+            .send(Message::SnapshotRefusal(SnapshotRefusal {
+                peer,
+                snapshot_id,
+                earliest_height,
+            }))
+            .map_err(|_| Error::ChannelWasClosed)
+    }
+
     /// Handled by [SyncWorker::handle_snapshot_chunk].
     pub fn snapshot_chunk(&self, peer: PeerId, sc: SnapshotChunk) -> Result<(), Error> {
         self.0
@@ -110,6 +155,9 @@ pub struct SyncWorker {
     /// we can trigger out of sync again,
     /// without recursing.
     channel_sender: SyncWorkerChannel,
+    /// Every peer refused our last slow sync request because they have pruned the blocks we
+    /// need, so the next request should be for a fast sync
+    prefer_fast_sync: bool,
 }
 
 impl SyncWorker {
@@ -133,6 +181,7 @@ impl SyncWorker {
             node_mode,
             channel,
             channel_sender,
+            prefer_fast_sync: false,
         }
     }
 
@@ -140,7 +189,9 @@ impl SyncWorker {
         tokio::spawn(async move {
             loop {
                 let out_of_sync = self.wait_for_out_of_sync().await?;
-                self.handle_out_of_sync(out_of_sync).await?;
+                let res = self.handle_out_of_sync(out_of_sync).await;
+                self.node.set_sync_status(SyncStatus::Idle);
+                res?;
 
                 tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
             }
@@ -169,23 +220,36 @@ impl SyncWorker {
         let snapshot_id = rand::random();
 
         let from_height = self.node.height() + BlockHeight(1);
-        let mut to_height = from_height + BlockHeight(self.chunk_size);
+        // Slow sync downloads a chunk from each peer at once
+        let mut to_height = std::cmp::min(
+            from_height + BlockHeight(self.chunk_size * download::MAX_DOWNLOAD_PEERS as u64),
+            max_seen_height + BlockHeight(1),
+        );
         let mut snapshot_kind = SnapshotKind::Slow;
 
+        let prefer_fast_sync = std::mem::take(&mut self.prefer_fast_sync);
         let far_enough_to_try_fast_sync =
-            max_seen_height.0 - self.node.height().0 > self.fast_sync_threshold;
-        if self.node_mode.is_prover() && far_enough_to_try_fast_sync {
-            match self.rollup_contract.block_height().await {
-                Ok(contract_height) => {
-                    let contract_height = BlockHeight(contract_height);
-                    if contract_height > to_height {
-                        to_height = contract_height;
-                        snapshot_kind = SnapshotKind::Fast;
+            prefer_fast_sync || max_seen_height.0 - self.node.height().0 > self.fast_sync_threshold;
+        if far_enough_to_try_fast_sync {
+            let fast_sync_height = if self.node_mode.is_prover() {
+                // Provers need every block after the contract's height, so they can prove them
+                match self.rollup_contract.block_height().await {
+                    Ok(contract_height) => Some(BlockHeight(contract_height)),
+                    Err(err) => {
+                        error!(?err, "Failed to get contract height");
+                        None
                     }
                 }
-                Err(err) => {
-                    error!(?err, "Failed to get contract height");
-                }
+            } else {
+                Some(max_seen_height)
+            };
+
+            // Peers that refused a slow sync can still serve a fast sync of any length
+            let worth_fast_syncing =
+                |h: &BlockHeight| *h > to_height || (prefer_fast_sync && *h >= from_height);
+            if let Some(fast_sync_height) = fast_sync_height.filter(worth_fast_syncing) {
+                to_height = fast_sync_height;
+                snapshot_kind = SnapshotKind::Fast;
             }
         };
 
@@ -206,6 +270,7 @@ impl SyncWorker {
             kind: snapshot_kind,
         };
         let request = snapshot_request;
+        self.node.set_sync_status(SyncStatus::AwaitingOffer);
         self.node
             .send_all(NetworkEvent::SnapshotRequest(request))
             .await;
@@ -217,20 +282,70 @@ impl SyncWorker {
                 return Ok(());
             },
         };
+        let Some(so) = so else {
+            warn!(
+                ?snapshot_id,
+                "Every peer refused the snapshot request, retrying with fast sync"
+            );
+            self.prefer_fast_sync = true;
+            return self.continue_sync();
+        };
 
-        self.handle_snapshot_offer(snapshot_kind, to_height, so)
-            .await?;
+        match snapshot_kind {
+            SnapshotKind::Fast => {
+                self.handle_snapshot_offer(snapshot_kind, to_height, so)
+                    .await?
+            }
+            SnapshotKind::Slow => {
+                self.download_blocks(snapshot_id, from_height, to_height, so)
+                    .await?;
+                self.continue_sync()?;
+            }
+        }
 
         Ok(())
     }
 
+    /// Trigger the next round of sync if we're still behind
+    fn continue_sync(&self) -> Result<(), Error> {
+        if !self.node.is_out_of_sync() {
+            info!("Finished synchronizing proposals");
+            return Ok(());
+        }
+
+        // Send a message rather than call [[Self::handle_out_of_sync]], so that we don't recurse
+        self.channel_sender.out_of_sync(self.node.max_height())
+    }
+
+    /// Wait for the first offer for `snapshot_id`. Returns `None` if every connected peer
+    /// refused the request instead
     async fn wait_for_snapshot_offer(
         &mut self,
         snapshot_id: SnapshotId,
-    ) -> Result<SnapshotOffer, Error> {
+    ) -> Result<Option<SnapshotOffer>, Error> {
+        let mut refused = HashSet::new();
+
         while let Some(msg) = self.channel.recv().await {
             match msg {
-                Message::SnapshotOffer(so) if so.snapshot_id == snapshot_id => return Ok(so),
+                Message::SnapshotOffer(so) if so.snapshot_id == snapshot_id => return Ok(Some(so)),
+                Message::SnapshotRefusal(SnapshotRefusal {
+                    peer,
+                    snapshot_id: id,
+                    earliest_height,
+                }) if id == snapshot_id => {
+                    info!(
+                        ?snapshot_id,
+                        ?peer,
+                        ?earliest_height,
+                        "Peer refused snapshot request"
+                    );
+                    refused.insert(peer);
+
+                    let peers = self.node.connected_peers();
+                    if peers.iter().all(|peer| refused.contains(peer)) {
+                        return Ok(None);
+                    }
+                }
                 _ => {}
             }
         }
@@ -259,6 +374,7 @@ impl SyncWorker {
             to_height,
             kind,
         };
+        self.node.set_sync_status(SyncStatus::AwaitingChunk);
         self.node
             .send(peer, NetworkEvent::SnapshotAccept(accept))
             .await;
@@ -300,6 +416,8 @@ impl SyncWorker {
         peer: PeerId,
         sc: SnapshotChunk,
     ) -> Result<(), Error> {
+        self.node.set_sync_status(SyncStatus::ApplyingChunk);
+
         match sc {
             SnapshotChunk::Slow(sc) => self.handle_snapshot_chunk_slow(peer, sc).await,
             SnapshotChunk::Fast(sc) => self.handle_snapshot_chunk_fast(peer, sc).await,
@@ -316,27 +434,8 @@ impl SyncWorker {
     ) -> Result<(), Error> {
         let proposal_len = chunk.len();
 
-        // Chunk can be very large, so we work on it in a blocking task
-        tokio::task::spawn_blocking({
-            let node = Arc::clone(&self.node);
-
-            move || {
-                chunk.sort_by_key(|b| b.content.header.height);
-
-                for block in chunk {
-                    let hash = block.hash();
-                    let height = block.content.header.height;
-                    match node.receive_proposal(block) {
-                        Ok(_) => {}
-                        Err(err) => {
-                            warn!(?err, ?hash, ?height, "Failed to receive proposal");
-                        }
-                    }
-                    node.ticker.tick()
-                }
-            }
-        })
-        .await?;
+        chunk.sort_by_key(|b| b.content.header.height);
+        self.apply_blocks(chunk).await?;
 
         info!(
             ?snapshot_id,
@@ -345,23 +444,18 @@ impl SyncWorker {
             "Applied snapshot proposals"
         );
 
-        if !self.node.is_out_of_sync() {
-            info!(?snapshot_id, "Finished synchronizing proposals");
-            return Ok(());
-        }
-
-        // Send a message rather than call [[Self::handle_out_of_sync]], so that we don't recurse
-        self.channel_sender.out_of_sync(self.node.max_height())?;
+        self.continue_sync()?;
 
         Ok(())
     }
 
     async fn handle_snapshot_chunk_fast(
         &mut self,
-        _peer: PeerId,
+        peer: PeerId,
         SnapshotChunkFast {
             snapshot_id: _,
             block,
+            successor,
             elements,
         }: SnapshotChunkFast,
     ) -> Result<(), Error> {
@@ -370,6 +464,32 @@ impl SyncWorker {
             return Ok(());
         };
 
+        if block.content.header.height <= self.node.height() {
+            warn!(
+                height = ?block.content.header.height,
+                "Fast snapshot chunk block is not ahead of us"
+            );
+            return Ok(());
+        }
+
+        // The elements are only checked against the block's root hash, so the block itself
+        // must come from somewhere we trust
+        let Some(anchor) = self.root_anchor(&block, successor.as_deref()).await else {
+            error!(
+                height = ?block.content.header.height,
+                root_hash = ?block.content.state.root_hash,
+                "Fast snapshot chunk root hash is not in the rollup contract or endorsed by the next block"
+            );
+            self.node
+                .report_misbehaviour(peer, Misbehaviour::BadSnapshotChunk);
+            return Ok(());
+        };
+        info!(
+            height = ?block.content.header.height,
+            ?anchor,
+            "Verified fast snapshot chunk root hash"
+        );
+
         let block_elements = block
             .content
             .state
@@ -394,6 +514,8 @@ impl SyncWorker {
                 .collect::<Vec<_>>();
             if tree.tree().root_hash_with(&elements) != block.content.state.root_hash {
                 error!("Fast snapshot chunk root hash mismatch");
+                self.node
+                    .report_misbehaviour(peer, Misbehaviour::BadSnapshotChunk);
                 return Ok(());
             }
 
@@ -413,21 +535,58 @@ impl SyncWorker {
                     ?block_elements_left_to_find,
                     "Fast snapshot chunk missing elements"
                 );
+                self.node
+                    .report_misbehaviour(peer, Misbehaviour::BadSnapshotChunk);
                 return Ok(());
             }
 
             tree.insert_batch(batch)?;
-            self.node
-                .block_cache
-                .lock()
-                .confirm(block.content.header.height - BlockHeight(1))
         }
 
-        self.node.receive_proposal(*block).map_err(Box::new)?;
+        // The block's root hash is anchored, so we commit it without checking its approvals,
+        // which needs the block it builds on. That block may not be at the height before it, if
+        // the heights in between were skipped
+        let height = block.content.header.height;
+        self.node
+            .commit_proposal(*block.clone())
+            .map_err(Box::new)?;
+        {
+            let mut block_cache = self.node.block_cache.lock();
+            block_cache.insert(*block);
+            block_cache.confirm(height);
+        }
         self.node.ticker.tick();
 
         Ok(())
     }
+
+    /// Find why we can trust the root hash of a block received in a fast snapshot chunk, if at all.
+    /// A leader's signature alone isn't enough, as the leader could sign any state
+    async fn root_anchor(&self, block: &Block, successor: Option<&Block>) -> Option<RootAnchor> {
+        match self.rollup_contract.root_hashes().await {
+            Ok(root_hashes) => {
+                if root_hashes.into_iter().any(|root_hash| {
+                    Element::from_be_bytes(root_hash.0) == block.content.state.root_hash
+                }) {
+                    return Some(RootAnchor::RollupContract);
+                }
+            }
+            Err(err) => error!(?err, "Failed to get contract root hashes"),
+        }
+
+        successor
+            .filter(|successor| self.node.is_final_with_successor(block, successor))
+            .map(|_| RootAnchor::FinalBlock)
+    }
+}
+
+/// Where the root hash of a fast snapshot chunk was verified
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RootAnchor {
+    /// The next block carries endorsements of the block by more than 2/3 of the stake
+    FinalBlock,
+    /// The root hash is one of the rollup contract's recent root hashes
+    RollupContract,
 }
 
 /// An out of sync node sent a snapshot request,
@@ -438,13 +597,35 @@ pub(crate) async fn handle_snapshot_request(
     snapshot_id: SnapshotId,
     from_height: BlockHeight,
     _to_height: BlockHeight,
-    _kind: SnapshotKind,
+    kind: SnapshotKind,
 ) -> Result<(), Error> {
     if node.is_out_of_sync() || from_height > node.height() {
         info!("Ignoring snapshot request, we're too far behind");
         return Ok(());
     }
 
+    // Slow sync needs every block from `from_height`, fast sync only needs the latest block
+    if let SnapshotKind::Slow = kind {
+        let pruned_height = node.pruned_height().map_err(Box::new)?;
+
+        if let Some(earliest_height) = pruned_height.filter(|h| from_height < *h) {
+            info!(
+                ?snapshot_id,
+                ?from_height,
+                ?earliest_height,
+                "Refusing snapshot request, the blocks have been pruned"
+            );
+
+            let refusal = crate::network::SnapshotRefusal {
+                snapshot_id,
+                earliest_height,
+            };
+            node.send(peer, NetworkEvent::SnapshotRefusal(refusal)).await;
+
+            return Ok(());
+        }
+    }
+
     info!(?snapshot_id, "Sending snapshot offer");
 
     let offer = crate::network::SnapshotOffer { snapshot_id };
@@ -531,23 +712,51 @@ async fn send_snapshot_chunk_fast(
     _from_height: BlockHeight,
     to_height: BlockHeight,
 ) -> Result<(), Error> {
-    let block = node.get_block(to_height).map_err(Box::new)?;
-
-    let elements = node
-        .notes_tree()
-        .read()
-        .tree()
-        .elements()
-        .filter_map(|(e, meta)| {
-            // We can't filter by from_height,
-            // because we don't know the height of the elements if they were fast-synced
-            if meta.inserted_in <= to_height.0 {
-                Some(*e)
-            } else {
-                None
+    // The block at `to_height` may have been skipped, the latest block before it has the
+    // same elements. We send the latest block up to `to_height` that is followed by a block at
+    // the next height, as that block's endorsements let the peer trust it
+    let mut final_block = None;
+    let mut successor = None;
+    for b in node
+        .fetch_blocks(..=to_height.next(), BlockListOrder::HighestToLowest)
+        .into_iterator()
+    {
+        let b = b.map_err(Box::new)?.into_block();
+        let height = b.content.header.height;
+        match successor.take() {
+            Some(s) if height <= to_height && s.content.header.height == height.next() => {
+                final_block = Some((b, s));
+                break;
             }
-        })
-        .collect::<Vec<_>>();
+            _ => successor = Some(b),
+        }
+    }
+
+    let elements = match &final_block {
+        Some((block, _)) => node
+            .notes_tree()
+            .read()
+            .tree()
+            .elements()
+            .filter_map(|(e, meta)| {
+                // We can't filter by from_height,
+                // because we don't know the height of the elements if they were fast-synced
+                if meta.inserted_in <= block.content.header.height.0 {
+                    Some(*e)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>(),
+        None => {
+            warn!(?to_height, "No final block to send in fast snapshot chunk");
+            Vec::new()
+        }
+    };
+
+    let (block, successor) = final_block
+        .map(|(block, successor)| (Box::new(block), Box::new(successor)))
+        .unzip();
 
     // Send snapshot chunk
     node.send(
@@ -555,7 +764,8 @@ async fn send_snapshot_chunk_fast(
         NetworkEvent::SnapshotChunk(crate::network::SnapshotChunk::Fast(
             crate::network::SnapshotChunkFast {
                 snapshot_id,
-                block: block.map(|b| Box::new(b.into_block())),
+                block,
+                successor,
                 elements,
             },
         )),
@@ -0,0 +1,414 @@
+//! Download a range of blocks from several peers at once.
+//!
+//! The range is split into chunks of `sync-chunk-size` blocks, and each peer that offered a
+//! snapshot is sent a [SnapshotAccept] for one chunk at a time. Chunks that time out, or are
+//! invalid, are re-requested from another peer, and the peer's misbehaviour is reported to the
+//! network, which bans peers that misbehave repeatedly. Peers that have misbehaved the least
+//! are downloaded from first.
+//! Chunks are applied in height order as soon as every chunk before them has arrived.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::Arc,
+};
+
+use libp2p::PeerId;
+use p2p2::Misbehaviour;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::{
+    block::Block,
+    network::{NetworkEvent, SnapshotAccept, SnapshotChunk, SnapshotChunkSlow, SnapshotKind},
+    types::{BlockHeight, SnapshotId},
+};
+
+use super::{Error, Message, SnapshotOffer, SyncStatus, SyncWorker};
+
+/// How long to keep collecting snapshot offers after the first one arrives
+const OFFER_WINDOW: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Maximum number of peers to download from at once
+pub(super) const MAX_DOWNLOAD_PEERS: usize = 8;
+
+/// A half-open range of block heights, `from..to`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ChunkRange {
+    from: BlockHeight,
+    to: BlockHeight,
+}
+
+struct InFlight {
+    range: ChunkRange,
+    deadline: Instant,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ChunkError {
+    /// A block was outside the requested range
+    UnexpectedHeight(BlockHeight),
+    /// A block didn't build on the block before it in the chunk
+    NotOnChain(BlockHeight),
+    /// A block wasn't signed by the leader for its height
+    InvalidSignature(BlockHeight),
+}
+
+fn split_range(from: BlockHeight, to: BlockHeight, chunk_size: u64) -> VecDeque<ChunkRange> {
+    let chunk_size = chunk_size.max(1);
+
+    (from.0..to.0)
+        .step_by(chunk_size as usize)
+        .map(|start| ChunkRange {
+            from: BlockHeight(start),
+            to: BlockHeight(std::cmp::min(start + chunk_size, to.0)),
+        })
+        .collect()
+}
+
+/// Sort the blocks and check they form a chain starting in `range`. Heights can be skipped, so
+/// each block must build on the block before it rather than be at the next height. Returns the
+/// height after the last block, which is before `range.to` if the peer didn't have every block
+fn check_chunk(
+    range: ChunkRange,
+    blocks: &mut Vec<Block>,
+    is_signed_by_leader: impl Fn(&Block) -> bool,
+) -> Result<BlockHeight, ChunkError> {
+    blocks.sort_by_key(|b| b.content.header.height);
+    blocks.dedup_by_key(|b| b.content.header.height);
+
+    let mut next_height = range.from;
+    let mut parent_hash = None;
+    for block in blocks.iter() {
+        let height = block.content.header.height;
+        if height < next_height || height >= range.to {
+            return Err(ChunkError::UnexpectedHeight(height));
+        }
+
+        if parent_hash.map_or(false, |hash| hash != block.content.header.last_block_hash) {
+            return Err(ChunkError::NotOnChain(height));
+        }
+
+        if !is_signed_by_leader(block) {
+            return Err(ChunkError::InvalidSignature(height));
+        }
+
+        next_height = height + BlockHeight(1);
+        parent_hash = Some(block.hash());
+    }
+
+    Ok(next_height)
+}
+
+impl SyncWorker {
+    /// Download `from_height..to_height` from the peers that offer a snapshot, starting with
+    /// `first_offer`
+    pub(super) async fn download_blocks(
+        &mut self,
+        snapshot_id: SnapshotId,
+        from_height: BlockHeight,
+        to_height: BlockHeight,
+        first_offer: SnapshotOffer,
+    ) -> Result<(), Error> {
+        let mut idle = self.collect_offers(snapshot_id, first_offer.peer).await?;
+        let mut peer_count = idle.len();
+
+        let mut pending = split_range(from_height, to_height, self.chunk_size);
+        let mut in_flight = HashMap::<PeerId, InFlight>::new();
+        let mut downloaded = BTreeMap::<BlockHeight, Vec<Block>>::new();
+        let mut next_height = from_height;
+        let mut last_hash = *self.node.block_cache.lock().hash();
+
+        info!(
+            ?snapshot_id,
+            ?from_height,
+            ?to_height,
+            peers = idle.len(),
+            chunks = pending.len(),
+            "Downloading blocks"
+        );
+
+        loop {
+            while !pending.is_empty() {
+                let Some(peer) = idle.pop_front() else {
+                    break;
+                };
+                let Some(range) = pending.pop_front() else {
+                    break;
+                };
+
+                self.node
+                    .send(
+                        peer,
+                        NetworkEvent::SnapshotAccept(SnapshotAccept {
+                            snapshot_id,
+                            from_height: range.from,
+                            to_height: range.to,
+                            kind: SnapshotKind::Slow,
+                        }),
+                    )
+                    .await;
+
+                let deadline = Instant::now() + self.timeout;
+                in_flight.insert(peer, InFlight { range, deadline });
+            }
+
+            let Some(next_deadline) = in_flight.values().map(|f| f.deadline).min() else {
+                // Either everything was downloaded, or there are no peers left to ask
+                break;
+            };
+            self.node.set_sync_status(SyncStatus::AwaitingChunk);
+
+            tokio::select! {
+                msg = self.channel.recv() => match msg {
+                    None => return Err(Error::ChannelWasClosed),
+                    Some(Message::SnapshotChunk(
+                        peer,
+                        SnapshotChunk::Slow(SnapshotChunkSlow { snapshot_id: id, chunk }),
+                    )) if id == snapshot_id => {
+                        let Some(InFlight { range, .. }) = in_flight.remove(&peer) else {
+                            continue;
+                        };
+                        if range.to <= next_height {
+                            // We've since found the heights were skipped
+                            idle.push_back(peer);
+                            continue;
+                        }
+
+                        let mut blocks = chunk;
+                        match check_chunk(range, &mut blocks, |b| self.node.is_signed_by_leader(b)) {
+                            Ok(end) if end == range.from => {
+                                // The peer doesn't have any of these blocks, so it won't have
+                                // any later ones either
+                                pending.push_front(range);
+                            }
+                            Ok(end) => {
+                                downloaded.insert(range.from, blocks);
+
+                                if end < range.to {
+                                    pending.push_front(ChunkRange { from: end, to: range.to });
+                                } else {
+                                    idle.push_back(peer);
+                                }
+                            }
+                            Err(err) => {
+                                warn!(?snapshot_id, ?peer, ?range, ?err, "Invalid snapshot chunk");
+                                self.node.report_misbehaviour(peer, Misbehaviour::BadSnapshotChunk);
+                                pending.push_front(range);
+                            }
+                        }
+                    }
+                    Some(Message::SnapshotOffer(offer)) if offer.snapshot_id == snapshot_id => {
+                        // A late offer, use the peer if we have room
+                        if peer_count < MAX_DOWNLOAD_PEERS {
+                            peer_count += 1;
+                            idle.push_back(offer.peer);
+                        }
+                    }
+                    Some(_) => {}
+                },
+                _ = tokio::time::sleep_until(next_deadline) => {
+                    let now = Instant::now();
+                    let timed_out = in_flight
+                        .iter()
+                        .filter(|(_, f)| f.deadline <= now)
+                        .map(|(peer, _)| *peer)
+                        .collect::<Vec<_>>();
+
+                    for peer in timed_out {
+                        let Some(InFlight { range, .. }) = in_flight.remove(&peer) else {
+                            continue;
+                        };
+
+                        warn!(?snapshot_id, ?peer, ?range, "Snapshot chunk timed out");
+                        self.node.report_misbehaviour(peer, Misbehaviour::SnapshotTimeout);
+                        pending.push_front(range);
+                    }
+                },
+            }
+
+            // Apply every chunk we have, up to the first gap. A chunk that builds on the last
+            // block we applied follows it even if there's a gap in height, as the heights in
+            // between were skipped
+            while let Some(entry) = downloaded.first_entry() {
+                let follows = *entry.key() == next_height
+                    || entry
+                        .get()
+                        .first()
+                        .map_or(false, |b| b.content.header.last_block_hash == last_hash);
+                if !follows {
+                    break;
+                }
+
+                let blocks = entry.remove();
+                let Some(last) = blocks.last() else {
+                    continue;
+                };
+                next_height = last.content.header.height + BlockHeight(1);
+                last_hash = last.hash();
+                pending.retain(|range| range.to > next_height);
+
+                self.apply_blocks(blocks).await?;
+            }
+        }
+
+        info!(
+            ?snapshot_id,
+            ?from_height,
+            applied_to = ?next_height,
+            missing_chunks = pending.len(),
+            "Finished downloading blocks"
+        );
+
+        Ok(())
+    }
+
+    /// Collect the peers that offer `snapshot_id`, least misbehaving first
+    async fn collect_offers(
+        &mut self,
+        snapshot_id: SnapshotId,
+        first_peer: PeerId,
+    ) -> Result<VecDeque<PeerId>, Error> {
+        let mut peers = vec![first_peer];
+
+        let window = tokio::time::sleep(OFFER_WINDOW);
+        tokio::pin!(window);
+
+        loop {
+            tokio::select! {
+                msg = self.channel.recv() => match msg {
+                    None => return Err(Error::ChannelWasClosed),
+                    Some(Message::SnapshotOffer(offer))
+                        if offer.snapshot_id == snapshot_id && !peers.contains(&offer.peer) =>
+                    {
+                        peers.push(offer.peer);
+                    }
+                    Some(_) => {}
+                },
+                _ = &mut window => break,
+            }
+        }
+
+        peers.sort_by(|a, b| self.node.peer_score(a).total_cmp(&self.node.peer_score(b)));
+        peers.truncate(MAX_DOWNLOAD_PEERS);
+
+        Ok(peers.into())
+    }
+
+    /// Pass each block to the node, in height order
+    pub(super) async fn apply_blocks(&self, blocks: Vec<Block>) -> Result<(), Error> {
+        self.node.set_sync_status(SyncStatus::ApplyingChunk);
+
+        // Chunks can be very large, so we work on them in a blocking task
+        tokio::task::spawn_blocking({
+            let node = Arc::clone(&self.node);
+
+            move || {
+                for block in blocks {
+                    let hash = block.hash();
+                    let height = block.content.header.height;
+                    match node.receive_proposal(block) {
+                        Ok(_) => {}
+                        Err(err) => {
+                            warn!(?err, ?hash, ?height, "Failed to receive proposal");
+                        }
+                    }
+                    node.ticker.tick()
+                }
+            }
+        })
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primitives::hash::CryptoHash;
+
+    use super::*;
+
+    /// Block `height` of a chain without skipped heights
+    fn new_block(height: u64) -> Block {
+        let parent_hash = match height {
+            0 => CryptoHash::default(),
+            _ => new_block(height - 1).hash(),
+        };
+
+        block_on(height, parent_hash)
+    }
+
+    fn block_on(height: u64, parent_hash: CryptoHash) -> Block {
+        let mut block = Block::default();
+        block.content.header.height = BlockHeight(height);
+        block.content.header.last_block_hash = parent_hash;
+        block
+    }
+
+    fn range(from: u64, to: u64) -> ChunkRange {
+        ChunkRange {
+            from: BlockHeight(from),
+            to: BlockHeight(to),
+        }
+    }
+
+    #[test]
+    fn split_range_into_chunks() {
+        let chunks = split_range(BlockHeight(1), BlockHeight(8), 3);
+        assert_eq!(
+            chunks.into_iter().collect::<Vec<_>>(),
+            vec![range(1, 4), range(4, 7), range(7, 8)]
+        );
+
+        assert!(split_range(BlockHeight(5), BlockHeight(5), 3).is_empty());
+    }
+
+    #[test]
+    fn check_chunk_heights() {
+        let signed = |_: &Block| true;
+
+        let mut blocks = vec![new_block(3), new_block(2), new_block(2)];
+        assert_eq!(
+            check_chunk(range(2, 5), &mut blocks, signed),
+            Ok(BlockHeight(4))
+        );
+        assert_eq!(blocks.len(), 2);
+
+        let mut blocks = vec![];
+        assert_eq!(
+            check_chunk(range(2, 5), &mut blocks, signed),
+            Ok(BlockHeight(2))
+        );
+
+        let mut blocks = vec![new_block(2), new_block(4)];
+        assert_eq!(
+            check_chunk(range(2, 5), &mut blocks, signed),
+            Err(ChunkError::NotOnChain(BlockHeight(4)))
+        );
+
+        // Height 3 was skipped
+        let mut blocks = vec![new_block(2), block_on(4, new_block(2).hash())];
+        assert_eq!(
+            check_chunk(range(2, 5), &mut blocks, signed),
+            Ok(BlockHeight(5))
+        );
+
+        let mut blocks = vec![new_block(1), new_block(2)];
+        assert_eq!(
+            check_chunk(range(2, 5), &mut blocks, signed),
+            Err(ChunkError::UnexpectedHeight(BlockHeight(1)))
+        );
+
+        let mut blocks = vec![new_block(2), new_block(3), new_block(4), new_block(5)];
+        assert_eq!(
+            check_chunk(range(2, 5), &mut blocks, signed),
+            Err(ChunkError::UnexpectedHeight(BlockHeight(5)))
+        );
+
+        let mut blocks = vec![new_block(2), new_block(3)];
+        assert_eq!(
+            check_chunk(range(2, 5), &mut blocks, |b| b.content.header.height.0 != 3),
+            Err(ChunkError::InvalidSignature(BlockHeight(3)))
+        );
+    }
+}
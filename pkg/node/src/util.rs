@@ -1,8 +1,12 @@
 extern crate rand;
 
+use std::{fs::OpenOptions, io::Write, path::Path};
+
 use libp2p::identity;
 use rand::RngCore;
 
+use crate::{Error, Result};
+
 pub(crate) fn generate_p2p_key() -> (identity::Keypair, [u8; 32]) {
     let mut bytes = [0u8; 32];
     rand::thread_rng().fill_bytes(&mut bytes);
@@ -10,3 +14,58 @@ pub(crate) fn generate_p2p_key() -> (identity::Keypair, [u8; 32]) {
     let keypair = identity::Keypair::ed25519_from_bytes(bytes).unwrap();
     (keypair, bytes)
 }
+
+/// Load the p2p key from `path`, or generate one and save it to `path` if it doesn't exist, so
+/// the node keeps its peer id across restarts
+pub(crate) fn load_or_generate_p2p_key(path: &Path) -> Result<identity::Keypair> {
+    if path.exists() {
+        let bytes: [u8; 32] = std::fs::read(path)?
+            .try_into()
+            .map_err(|_| Error::InvalidP2pKey(path.to_owned()))?;
+
+        return identity::Keypair::ed25519_from_bytes(bytes)
+            .map_err(|_| Error::InvalidP2pKey(path.to_owned()));
+    }
+
+    let (keypair, bytes) = generate_p2p_key();
+
+    // Create the file with its final permissions, so the key is never readable by other users
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(&bytes)?;
+
+    Ok(keypair)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn p2p_key_is_persisted() {
+        let dir = tempdir::TempDir::new("p2p_key").unwrap();
+        let path = dir.path().join("p2p_key");
+
+        let keypair = load_or_generate_p2p_key(&path).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let reloaded = load_or_generate_p2p_key(&path).unwrap();
+        assert_eq!(keypair.public(), reloaded.public());
+
+        std::fs::write(&path, [0u8; 8]).unwrap();
+        assert!(matches!(
+            load_or_generate_p2p_key(&path),
+            Err(Error::InvalidP2pKey(_))
+        ));
+    }
+}
@@ -5,6 +5,8 @@ use block_store::StoreList;
 use zk_circuits::{constants::MERKLE_TREE_DEPTH, data::SnarkWitness, CircuitKind};
 use zk_primitives::Element;
 
+use crate::cache::VerifiedProofCache;
+use crate::mempool::MintOrBurn;
 use crate::Mode;
 use crate::{
     constants::RECENT_ROOT_COUNT, types::BlockHeight, BlockFormat, Error, PersistentMerkleTree,
@@ -13,8 +15,14 @@ use crate::{
 
 pub type UtxoProof = zk_circuits::data::UTXOProof<MERKLE_TREE_DEPTH>;
 
+impl MintOrBurn for UtxoProof {
+    fn is_mint_or_burn(&self) -> bool {
+        self.mb_hash != Element::ZERO && self.mb_value != Element::ZERO
+    }
+}
+
 /// Validate a UTXO txn, we check the following:
-/// - The proof is valid
+/// - The proof is valid (unless it is already in `verified_proofs`)
 /// - The recent root is recent enough
 /// - The input notes are not already spent (not in tree)
 /// - The output notes do not already exist (not in tree)
@@ -24,13 +32,87 @@ pub fn validate_txn(
     height: BlockHeight,
     block_store: &BlockStore<BlockFormat>,
     notes_tree: &PersistentMerkleTree,
+    verified_proofs: &VerifiedProofCache,
 ) -> Result<()> {
-    let SnarkWitness::V1(witness) = utxo.to_snark_witness();
+    match txn_failures(mode, utxo, height, block_store, notes_tree, verified_proofs)?
+        .into_iter()
+        .next()
+    {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Run every check in [`validate_txn`] except the proof check, for txns whose proofs have
+/// already been verified (e.g. with [`UtxoProof::verify_batch`])
+pub fn validate_txn_state(
+    mode: Mode,
+    utxo: &UtxoProof,
+    height: BlockHeight,
+    block_store: &BlockStore<BlockFormat>,
+    notes_tree: &PersistentMerkleTree,
+) -> Result<()> {
+    match txn_state_failures(mode, utxo, height, block_store, notes_tree)?
+        .into_iter()
+        .next()
+    {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Run every check in [`validate_txn`], returning each check that failed (in the same order
+/// [`validate_txn`] checks them) instead of only the first
+pub fn txn_failures(
+    mode: Mode,
+    utxo: &UtxoProof,
+    height: BlockHeight,
+    block_store: &BlockStore<BlockFormat>,
+    notes_tree: &PersistentMerkleTree,
+    verified_proofs: &VerifiedProofCache,
+) -> Result<Vec<Error>> {
+    let mut failures = Vec::new();
 
-    if !witness.verify(CircuitKind::Utxo) {
-        return Err(Error::InvalidProof);
+    if !verify_txn_proof(utxo, verified_proofs) {
+        failures.push(Error::InvalidProof);
     }
 
+    failures.extend(txn_state_failures(
+        mode,
+        utxo,
+        height,
+        block_store,
+        notes_tree,
+    )?);
+
+    Ok(failures)
+}
+
+/// Whether the txn's proof is valid, skipping proofs we've already verified
+pub fn verify_txn_proof(utxo: &UtxoProof, verified_proofs: &VerifiedProofCache) -> bool {
+    if verified_proofs.contains(utxo) {
+        return true;
+    }
+
+    let SnarkWitness::V1(witness) = utxo.to_snark_witness();
+    let valid = witness.verify(CircuitKind::Utxo);
+    if valid {
+        verified_proofs.insert(utxo);
+    }
+
+    valid
+}
+
+/// The checks in [`txn_failures`] that don't need the proof to be verified
+fn txn_state_failures(
+    mode: Mode,
+    utxo: &UtxoProof,
+    height: BlockHeight,
+    block_store: &BlockStore<BlockFormat>,
+    notes_tree: &PersistentMerkleTree,
+) -> Result<Vec<Error>> {
+    let mut failures = Vec::new();
+
     // No need to check recent roots if recent_root is zero
     // TODO: are we defo this is secure?
     if utxo.recent_root != Element::ZERO {
@@ -50,7 +132,7 @@ pub fn validate_txn(
             .collect::<Result<Vec<_>>>()?;
 
         if !recent_roots.iter().any(|r| *r == utxo.recent_root) && !mode.is_prover() {
-            return Err(Error::UtxoRootIsNotRecentEnough {
+            failures.push(Error::UtxoRootIsNotRecentEnough {
                 utxo_recent_root: utxo.recent_root,
                 recent_roots,
                 txn_hash: utxo.hash(),
@@ -63,11 +145,9 @@ pub fn validate_txn(
 
     for leaf in utxo.input_leaves {
         if leaf >= Element::MODULUS {
-            return Err(Error::InvalidElementSize { element: leaf });
-        }
-
-        if leaf != Element::ZERO && tree.contains_element(&leaf) {
-            return Err(Error::NoteAlreadySpent {
+            failures.push(Error::InvalidElementSize { element: leaf });
+        } else if leaf != Element::ZERO && tree.contains_element(&leaf) {
+            failures.push(Error::NoteAlreadySpent {
                 spent_note: leaf,
                 failing_txn_hash: utxo.hash(),
             });
@@ -76,13 +156,11 @@ pub fn validate_txn(
 
     for leaf in utxo.output_leaves {
         if leaf >= Element::MODULUS {
-            return Err(Error::InvalidElementSize { element: leaf });
-        }
-
-        if leaf != Element::ZERO && tree.contains_element(&leaf) {
-            return Err(Error::OutputNoteExists { output_note: leaf });
+            failures.push(Error::InvalidElementSize { element: leaf });
+        } else if leaf != Element::ZERO && tree.contains_element(&leaf) {
+            failures.push(Error::OutputNoteExists { output_note: leaf });
         }
     }
 
-    Ok(())
+    Ok(failures)
 }
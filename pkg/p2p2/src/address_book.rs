@@ -0,0 +1,366 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// The most addresses kept for a peer, and the most peers kept in the address book
+const MAX_ADDRESSES_PER_PEER: usize = 8;
+const MAX_PEERS: usize = 1000;
+
+/// Peers are forgotten once they've been dialed this many times without connecting
+const MAX_FAILED_DIALS: u32 = 10;
+
+/// Addresses of peers we've connected to, saved to disk so they can be redialed after a restart
+pub(crate) struct AddressBook {
+    /// Saves the address book in the background, if it has a path
+    writer: Option<watch::Sender<Vec<u8>>>,
+    peers: HashMap<PeerId, Peer>,
+    /// Addresses from the `dial` config, which are redialed even if we don't know their peer id
+    bootstrap: Vec<(Multiaddr, Backoff)>,
+    min_backoff: Duration,
+    max_backoff: Duration,
+}
+
+#[derive(Default)]
+struct Peer {
+    addresses: Vec<Multiaddr>,
+    backoff: Backoff,
+}
+
+/// When to next dial a peer. Dials are assumed to fail until the peer connects, so each dial
+/// doubles the delay until the next one
+#[derive(Default)]
+struct Backoff {
+    dials: u32,
+    next_dial: Option<Instant>,
+}
+
+impl Backoff {
+    fn is_due(&self, now: Instant) -> bool {
+        self.next_dial.map_or(true, |next_dial| next_dial <= now)
+    }
+
+    fn dialed(&mut self, now: Instant, min: Duration, max: Duration) {
+        self.next_dial = Some(now + backoff(self.dials, min, max));
+        self.dials = self.dials.saturating_add(1);
+    }
+}
+
+/// The delay before redialing a peer that has been dialed `dials` times without connecting
+fn backoff(dials: u32, min: Duration, max: Duration) -> Duration {
+    min.saturating_mul(2u32.saturating_pow(dials)).min(max)
+}
+
+impl AddressBook {
+    /// Load the address book from `path`, or start with an empty one if it doesn't exist
+    pub(crate) fn load(
+        path: Option<PathBuf>,
+        bootstrap: impl IntoIterator<Item = Multiaddr>,
+        min_backoff: Duration,
+        max_backoff: Duration,
+    ) -> Self {
+        let peers = path
+            .as_ref()
+            .filter(|path| path.exists())
+            .and_then(|path| match Self::read(path) {
+                Ok(peers) => Some(peers),
+                Err(err) => {
+                    warn!(?err, ?path, "Failed to load address book");
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        info!(peers = peers.len(), "Loaded address book");
+
+        Self {
+            writer: path.map(spawn_writer),
+            peers,
+            bootstrap: bootstrap
+                .into_iter()
+                .map(|addr| (addr, Backoff::default()))
+                .collect(),
+            min_backoff,
+            max_backoff,
+        }
+    }
+
+    fn read(path: &Path) -> std::io::Result<HashMap<PeerId, Peer>> {
+        let file: HashMap<String, Vec<String>> =
+            serde_json::from_slice(&std::fs::read(path)?).map_err(std::io::Error::from)?;
+
+        Ok(file
+            .into_iter()
+            .filter_map(|(peer_id, addresses)| {
+                let peer = Peer {
+                    addresses: addresses
+                        .iter()
+                        .filter_map(|addr| addr.parse().ok())
+                        .filter(is_dialable)
+                        .collect(),
+                    backoff: Backoff::default(),
+                };
+                Some((peer_id.parse().ok()?, peer))
+            })
+            .collect())
+    }
+
+    fn save(&self) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+
+        let file = self
+            .peers
+            .iter()
+            .map(|(peer_id, peer)| {
+                let addresses = peer.addresses.iter().map(ToString::to_string).collect();
+                (peer_id.to_string(), addresses)
+            })
+            .collect::<HashMap<String, Vec<String>>>();
+
+        match serde_json::to_vec(&file) {
+            Ok(data) => {
+                writer.send_replace(data);
+            }
+            Err(err) => warn!(?err, "Failed to serialize address book"),
+        }
+    }
+
+    /// Add addresses a peer can be dialed at, e.g. the listen addresses it reports with identify.
+    /// Addresses that can only be dialed from the peer's own machine or network link are ignored
+    pub(crate) fn add_addresses(&mut self, peer_id: PeerId, addresses: Vec<Multiaddr>) {
+        let addresses = addresses
+            .into_iter()
+            .filter(is_dialable)
+            .collect::<Vec<_>>();
+        if addresses.is_empty()
+            || (!self.peers.contains_key(&peer_id) && self.peers.len() >= MAX_PEERS)
+        {
+            return;
+        }
+
+        let peer = self.peers.entry(peer_id).or_default();
+        let mut changed = false;
+        for addr in addresses {
+            if !peer.addresses.contains(&addr) && peer.addresses.len() < MAX_ADDRESSES_PER_PEER {
+                peer.addresses.push(addr);
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.save();
+        }
+    }
+
+    /// Reset the backoff of a peer we've connected to, connected at `address` if we dialed it
+    pub(crate) fn connected(&mut self, peer_id: &PeerId, address: Option<&Multiaddr>) {
+        if let Some(peer) = self.peers.get_mut(peer_id) {
+            peer.backoff = Backoff::default();
+        }
+
+        if let Some(address) = address {
+            for (addr, backoff) in &mut self.bootstrap {
+                if addr == address {
+                    *backoff = Backoff::default();
+                }
+            }
+        }
+    }
+
+    /// Peers (and bootstrap addresses) that are due to be redialed, at most `count`. Each one
+    /// returned is treated as dialed, so isn't returned again until its backoff has passed.
+    /// Peers that still haven't connected after [`MAX_FAILED_DIALS`] dials are forgotten
+    pub(crate) fn due(
+        &mut self,
+        now: Instant,
+        is_connected: impl Fn(&PeerId) -> bool,
+        is_connected_addr: impl Fn(&Multiaddr) -> bool,
+        count: usize,
+    ) -> Vec<Dial> {
+        let (min, max) = (self.min_backoff, self.max_backoff);
+
+        let peer_count = self.peers.len();
+        self.peers.retain(|peer_id, peer| {
+            let failed = peer.backoff.dials >= MAX_FAILED_DIALS && peer.backoff.is_due(now);
+            if failed {
+                info!(
+                    ?peer_id,
+                    dials = peer.backoff.dials,
+                    "Forgetting unreachable peer"
+                );
+            }
+            !failed
+        });
+        if self.peers.len() != peer_count {
+            self.save();
+        }
+
+        let bootstrap = self
+            .bootstrap
+            .iter_mut()
+            .filter(|(addr, backoff)| backoff.is_due(now) && !is_connected_addr(addr))
+            .map(|(addr, backoff)| (Dial::Address(addr.clone()), backoff));
+
+        let peers = self
+            .peers
+            .iter_mut()
+            .filter(|(peer_id, peer)| {
+                !peer.addresses.is_empty() && peer.backoff.is_due(now) && !is_connected(peer_id)
+            })
+            .map(|(peer_id, peer)| {
+                let dial = Dial::Peer(*peer_id, peer.addresses.clone());
+                (dial, &mut peer.backoff)
+            });
+
+        bootstrap
+            .chain(peers)
+            .take(count)
+            .map(|(dial, backoff)| {
+                backoff.dialed(now, min, max);
+                dial
+            })
+            .collect()
+    }
+}
+
+/// Whether an address can be dialed from other machines. Loopback, unspecified and link-local
+/// IP addresses are only reachable from the peer's own machine or network link
+fn is_dialable(addr: &Multiaddr) -> bool {
+    match addr.iter().next() {
+        Some(Protocol::Ip4(ip)) => !(ip.is_loopback() || ip.is_unspecified() || ip.is_link_local()),
+        Some(Protocol::Ip6(ip)) => {
+            // fe80::/10
+            let is_link_local = ip.segments()[0] & 0xffc0 == 0xfe80;
+            !(ip.is_loopback() || ip.is_unspecified() || is_link_local)
+        }
+        _ => true,
+    }
+}
+
+/// Write each new version of the address book to `path`, without blocking the network's event
+/// loop. Versions written while the previous write is in progress are skipped, except the latest
+fn spawn_writer(path: PathBuf) -> watch::Sender<Vec<u8>> {
+    let (tx, mut rx) = watch::channel(Vec::new());
+
+    tokio::spawn(async move {
+        while rx.changed().await.is_ok() {
+            let data = rx.borrow_and_update().clone();
+            if let Err(err) = tokio::fs::write(&path, data).await {
+                warn!(?err, ?path, "Failed to save address book");
+            }
+        }
+    });
+
+    tx
+}
+
+/// A peer to redial
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Dial {
+    /// A peer whose id we know
+    Peer(PeerId, Vec<Multiaddr>),
+    /// A bootstrap address, whose peer id we may not know
+    Address(Multiaddr),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN: Duration = Duration::from_secs(1);
+    const MAX: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        assert_eq!(backoff(0, MIN, MAX), Duration::from_secs(1));
+        assert_eq!(backoff(1, MIN, MAX), Duration::from_secs(2));
+        assert_eq!(backoff(5, MIN, MAX), Duration::from_secs(32));
+        assert_eq!(backoff(6, MIN, MAX), MAX);
+        assert_eq!(backoff(u32::MAX, MIN, MAX), MAX);
+    }
+
+    #[test]
+    fn redial_with_backoff() {
+        let bootstrap: Multiaddr = "/ip4/127.0.0.1/tcp/5001".parse().unwrap();
+        let mut book = AddressBook::load(None, [bootstrap.clone()], MIN, MAX);
+
+        let peer_id = PeerId::random();
+        let addr: Multiaddr = "/ip4/10.0.0.2/tcp/5002".parse().unwrap();
+        book.add_addresses(peer_id, vec![addr.clone(), addr.clone()]);
+
+        let now = Instant::now();
+        let due = book.due(now, |_| false, |_| false, 10);
+        assert_eq!(
+            due,
+            vec![
+                Dial::Address(bootstrap.clone()),
+                Dial::Peer(peer_id, vec![addr])
+            ]
+        );
+
+        // Not redialed until the backoff has passed
+        assert_eq!(book.due(now, |_| false, |_| false, 10), vec![]);
+        assert_eq!(book.due(now + MIN, |_| false, |_| false, 10).len(), 2);
+        assert_eq!(book.due(now + MIN * 2, |_| false, |_| false, 10), vec![]);
+
+        // Connecting resets the backoff, but connected peers aren't redialed
+        book.connected(&peer_id, Some(&bootstrap));
+        assert_eq!(book.due(now + MIN, |_| true, |_| true, 10), vec![]);
+        assert_eq!(book.due(now + MIN, |_| false, |_| false, 1).len(), 1);
+    }
+
+    #[test]
+    fn ignore_local_addresses() {
+        let mut book = AddressBook::load(None, [], MIN, MAX);
+
+        let peer_id = PeerId::random();
+        let addr: Multiaddr = "/ip4/192.168.1.2/tcp/5002".parse().unwrap();
+        let local = [
+            "/ip4/127.0.0.1/tcp/5002",
+            "/ip4/0.0.0.0/tcp/5002",
+            "/ip4/169.254.1.2/tcp/5002",
+            "/ip6/::1/tcp/5002",
+            "/ip6/fe80::1/tcp/5002",
+        ];
+        let mut addresses = local
+            .iter()
+            .map(|addr| addr.parse().unwrap())
+            .collect::<Vec<Multiaddr>>();
+        addresses.push(addr.clone());
+        book.add_addresses(peer_id, addresses);
+
+        let due = book.due(Instant::now(), |_| false, |_| false, 10);
+        assert_eq!(due, vec![Dial::Peer(peer_id, vec![addr])]);
+
+        // A peer with only local addresses isn't added at all
+        let mut book = AddressBook::load(None, [], MIN, MAX);
+        book.add_addresses(peer_id, vec![local[0].parse().unwrap()]);
+        assert!(book.peers.is_empty());
+    }
+
+    #[test]
+    fn forget_unreachable_peers() {
+        let mut book = AddressBook::load(None, [], MIN, MAX);
+
+        let peer_id = PeerId::random();
+        let addr: Multiaddr = "/ip4/10.0.0.2/tcp/5002".parse().unwrap();
+        book.add_addresses(peer_id, vec![addr]);
+
+        // Far enough apart that the backoff has always passed
+        let mut now = Instant::now();
+        for _ in 0..MAX_FAILED_DIALS {
+            assert_eq!(book.due(now, |_| false, |_| false, 10).len(), 1);
+            now += MAX;
+        }
+
+        assert_eq!(book.due(now, |_| false, |_| false, 10), vec![]);
+        assert!(book.peers.is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+use core::fmt;
+use std::{
+    collections::{HashMap, VecDeque},
+    task::{Context, Poll, Waker},
+    time::Instant,
+};
+
+use libp2p::{
+    core::Endpoint,
+    swarm::{
+        dummy, CloseConnection, ConnectionDenied, ConnectionId, NetworkBehaviour, PollParameters,
+        THandler, THandlerInEvent, ToSwarm,
+    },
+    Multiaddr, PeerId,
+};
+
+/// Temporarily bans peers, closing their connections and denying new ones until the ban ends
+#[derive(Debug, Default)]
+pub struct Behaviour {
+    banned: HashMap<PeerId, Instant>,
+    close_connections: VecDeque<PeerId>,
+    waker: Option<Waker>,
+}
+
+impl Behaviour {
+    /// Ban `peer` until `until`, closing its connections
+    pub fn ban(&mut self, peer: PeerId, until: Instant) {
+        self.banned.insert(peer, until);
+        self.close_connections.push_back(peer);
+
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    pub fn is_banned(&self, peer: &PeerId) -> bool {
+        self.banned
+            .get(peer)
+            .map_or(false, |until| *until > Instant::now())
+    }
+
+    fn check(&mut self, peer: PeerId, addr: &Multiaddr) -> Result<(), ConnectionDenied> {
+        if self.is_banned(&peer) {
+            return Err(ConnectionDenied::new(Banned {
+                peer,
+                addr: addr.clone(),
+            }));
+        }
+
+        // The ban has ended
+        self.banned.remove(&peer);
+        Ok(())
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type OutEvent = ();
+
+    fn on_swarm_event(&mut self, _event: libp2p::swarm::FromSwarm<Self::ConnectionHandler>) {
+        // do nothing
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _peer_id: PeerId,
+        _connection_id: ConnectionId,
+        _event: libp2p::swarm::THandlerOutEvent<Self>,
+    ) {
+        #[cfg(debug_assertions)]
+        {
+            unreachable!()
+        }
+
+        #[cfg(not(debug_assertions))]
+        {
+            tracing::warn!(
+                "ban::Behaviour::on_connection_handler_event called, which should be impossible"
+            )
+        }
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        peer: PeerId,
+        _local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.check(peer, remote_addr)?;
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        _role_override: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.check(peer, addr)?;
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+        _: &mut impl PollParameters,
+    ) -> Poll<ToSwarm<Self::OutEvent, THandlerInEvent<Self>>> {
+        if let Some(peer) = self.close_connections.pop_front() {
+            return Poll::Ready(ToSwarm::CloseConnection {
+                peer_id: peer,
+                connection: CloseConnection::All,
+            });
+        }
+
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+#[derive(Debug)]
+pub struct Banned {
+    peer: PeerId,
+    addr: Multiaddr,
+}
+
+impl fmt::Display for Banned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "peer {} (at addr {}) is banned", self.peer, self.addr)
+    }
+}
+
+impl std::error::Error for Banned {}
@@ -1,8 +1,8 @@
 use super::protocol::PolyProtocol;
 use borsh::{BorshDeserialize, BorshSerialize};
 use libp2p::{
-    request_response,
-    swarm::{behaviour::toggle::Toggle, keep_alive, NetworkBehaviour},
+    gossipsub, identify, request_response,
+    swarm::{keep_alive, NetworkBehaviour},
 };
 
 #[derive(NetworkBehaviour)]
@@ -11,6 +11,9 @@ where
     NetworkEvent: Clone + Sync + Send + BorshSerialize + BorshDeserialize + 'static,
 {
     pub rr: request_response::Behaviour<PolyProtocol<NetworkEvent>>,
+    pub gossipsub: gossipsub::Behaviour,
+    pub identify: identify::Behaviour,
     pub keep_alive: keep_alive::Behaviour,
-    pub whitelist: Toggle<whitelist_ips::Behaviour>,
+    pub whitelist: whitelist_ips::Behaviour,
+    pub ban: crate::ban::Behaviour,
 }
@@ -1,7 +1,9 @@
 use borsh::{BorshDeserialize, BorshSerialize};
-use libp2p::{Multiaddr, PeerId};
+use libp2p::{gossipsub::MessageAcceptance, Multiaddr, PeerId};
 use tokio::sync::oneshot;
 
+use crate::{GossipId, Misbehaviour};
+
 /// A command that can be sent to a running P2P node
 #[derive(Debug)]
 pub enum Command<NetworkEvent>
@@ -17,4 +19,16 @@ where
     /// Send a message to another peer, Sender will respond when response
     /// received
     Send(PeerId, NetworkEvent, oneshot::Sender<()>),
+
+    /// Publish a message to every peer subscribed to the topic
+    Publish(&'static str, NetworkEvent),
+
+    /// Report whether a gossip message is valid, valid messages are forwarded to other peers
+    ReportGossip(GossipId, MessageAcceptance),
+
+    /// Add misbehaviour to a peer's score, banning the peer if it reaches the ban threshold
+    ReportMisbehaviour(PeerId, Misbehaviour),
+
+    /// Replace the IP allow and deny lists, closing connections that are no longer allowed
+    SetIpFilter(whitelist_ips::Config),
 }
@@ -1,4 +1,8 @@
-use std::{path::Path, sync::OnceLock, collections::HashSet, net::IpAddr};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::OnceLock,
+};
 
 use figment::{
     providers::{Env, Format, Toml},
@@ -6,6 +10,7 @@ use figment::{
 };
 use libp2p::Multiaddr;
 use serde::{Deserialize, Deserializer};
+use whitelist_ips::IpNet;
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 #[non_exhaustive]
@@ -24,13 +29,36 @@ pub struct Config {
     /// 0 means "no timeout" (i.e. `u64::MAX` seconds)
     pub idle_timeout_secs: u64,
 
-    /// Explicitly whitelisted IP addresses
+    /// Explicitly whitelisted IP addresses or CIDR ranges (e.g. `10.0.0.0/8`)
     ///
-    /// Any IP address that isn't in this set will be banned (connections will be immediately
-    /// rejected)
+    /// Inbound connections from any IP address that isn't in one of these ranges will be
+    /// rejected
     ///
     /// If empty, whitelisting is disabled (i.e. all IPs are allowed)
-    pub whitelisted_ips: HashSet<IpAddr>,
+    pub whitelisted_ips: HashSet<IpNet>,
+
+    /// IP addresses or CIDR ranges that connections to and from are always rejected
+    pub denied_ips: HashSet<IpNet>,
+
+    /// The number of peers to stay connected to. While we have fewer, known peers are redialed
+    pub target_peers: usize,
+
+    /// The number of seconds to wait before redialing a peer we couldn't connect to, doubled
+    /// after each failed dial
+    pub redial_backoff_min_secs: u64,
+
+    /// The maximum number of seconds to wait before redialing a peer
+    pub redial_backoff_max_secs: u64,
+
+    /// The number of messages per second each peer can send, by message kind. Kinds that
+    /// aren't listed aren't rate limited
+    pub rate_limits: HashMap<String, u32>,
+
+    /// Peers are banned once their misbehaviour score reaches this
+    pub ban_threshold: u32,
+
+    /// The number of seconds a peer is banned for
+    pub ban_duration_secs: u64,
 }
 
 impl Default for Config {
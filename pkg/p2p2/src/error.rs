@@ -8,6 +8,9 @@ pub enum Error {
     #[error("Tansport error: {0}")]
     Transport(#[from] libp2p::TransportError<std::io::Error>),
 
+    #[error("Gossip error: {0}")]
+    Gossip(String),
+
     #[error("Channel error")]
     ChannelError(String),
 }
@@ -0,0 +1,26 @@
+use libp2p::{gossipsub, PeerId};
+use sha2::{Digest, Sha256};
+
+/// Events that are broadcast to the whole network with gossipsub, so they reach peers we're not
+/// connected to
+pub trait Gossip {
+    /// Every topic events can be published on, these are subscribed to when the network starts
+    const TOPICS: &'static [&'static str];
+
+    /// The topic to publish this event on, or `None` if it's only sent to connected peers
+    fn topic(&self) -> Option<&'static str>;
+}
+
+/// A gossip message received from the network, which isn't forwarded to other peers until it
+/// has been validated with [`crate::Network::report_gossip`]
+#[derive(Debug, Clone)]
+pub struct GossipId {
+    pub(crate) message_id: gossipsub::MessageId,
+    pub(crate) propagation_source: PeerId,
+}
+
+/// Messages are identified by their content, so the same event published by different peers is
+/// only delivered once
+pub(crate) fn message_id(message: &gossipsub::Message) -> gossipsub::MessageId {
+    gossipsub::MessageId::from(Sha256::digest(&message.data).to_vec())
+}
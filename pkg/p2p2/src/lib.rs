@@ -1,14 +1,22 @@
 #![feature(once_cell)]
 #![deny(clippy::disallowed_methods)]
 
+mod address_book;
+mod ban;
 mod behaviour;
 mod command;
 mod config;
 mod error;
+mod gossip;
 mod network;
+mod peer_score;
 mod protocol;
 mod transport;
 
 pub use config::Config;
 pub use error::{Error, Result};
+pub use gossip::{Gossip, GossipId};
+pub use libp2p::gossipsub::MessageAcceptance;
 pub use network::Network;
+pub use peer_score::{MessageKind, Misbehaviour};
+pub use whitelist_ips::IpNet;
@@ -1,17 +1,23 @@
 use crate::{
+    address_book::{AddressBook, Dial},
     behaviour::{Behaviour, BehaviourEvent},
     command::Command,
     error::Result,
+    gossip::{message_id, Gossip, GossipId},
+    peer_score::{MessageKind, Misbehaviour, PeerScores},
     protocol::{PolyProtocol, Request, Response},
     transport::create_transport,
-    Error,
+    Config, Error, IpNet,
 };
 use borsh::{BorshDeserialize, BorshSerialize};
 use futures_util::StreamExt;
 use libp2p::{
+    core::ConnectedPoint,
+    gossipsub::{self, MessageAcceptance},
+    identify,
     identity::Keypair,
     request_response,
-    swarm::{keep_alive, SwarmBuilder, SwarmEvent},
+    swarm::{dial_opts::DialOpts, keep_alive, Swarm, SwarmBuilder, SwarmEvent},
     Multiaddr, PeerId,
 };
 use parking_lot::Mutex;
@@ -19,16 +25,24 @@ use std::{
     collections::{HashMap, HashSet},
     fmt::Debug,
     marker::PhantomData,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use std::{net::IpAddr, sync::Arc};
 use tokio::{select, sync::mpsc, sync::oneshot, sync::Mutex as AsyncMutex};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+use wire_message::WireMessage;
+
+/// How often to check whether peers need to be redialed
+const REDIAL_INTERVAL: Duration = Duration::from_secs(1);
 
 pub struct Network<NetworkEvent>
 where
     NetworkEvent: Debug + Clone + Send + BorshSerialize + BorshDeserialize + 'static,
 {
-    netin_rx: AsyncMutex<mpsc::UnboundedReceiver<(PeerId, NetworkEvent)>>,
+    netin_rx: AsyncMutex<mpsc::UnboundedReceiver<(PeerId, NetworkEvent, Option<GossipId>)>>,
+    /// Peers whose last connection has closed
+    disconnected_rx: AsyncMutex<mpsc::UnboundedReceiver<PeerId>>,
     netout_tx: mpsc::UnboundedSender<Command<NetworkEvent>>,
     local_peer_id: PeerId,
     shared: Arc<NetworkShared>,
@@ -36,13 +50,27 @@ where
 
 impl<NetworkEvent> Network<NetworkEvent>
 where
-    NetworkEvent: Debug + Clone + Sync + Send + BorshSerialize + BorshDeserialize + 'static,
+    NetworkEvent: Debug
+        + Clone
+        + Sync
+        + Send
+        + BorshSerialize
+        + BorshDeserialize
+        + Gossip
+        + MessageKind
+        + 'static,
 {
+    /// Create the network and start listening and dialing. Peers we connect to are saved to the
+    /// address book at `address_book_path`, and redialed if the connection is lost. If
+    /// `on_connect` is set, it's sent to each peer when a connection to the peer is established.
+    /// Gossip messages larger than `max_gossip_size` bytes are dropped, so it must fit the
+    /// largest event, e.g. a full block
     pub fn new(
         keypair: &Keypair,
-        listenaddrs: impl Iterator<Item = Multiaddr>,
-        dialaddrs: impl Iterator<Item = Multiaddr>,
-        whitelisted_ips: HashSet<IpAddr>,
+        config: &Config,
+        address_book_path: Option<PathBuf>,
+        on_connect: Option<NetworkEvent>,
+        max_gossip_size: usize,
     ) -> Result<Network<NetworkEvent>> {
         let local_peer_id = PeerId::from(keypair.public());
         let transport = create_transport(keypair);
@@ -51,18 +79,35 @@ where
             request_response::ProtocolSupport::Full,
         )];
         let rr_config = request_response::Config::default();
+
+        let gossipsub_config = gossipsub::ConfigBuilder::default()
+            .validation_mode(gossipsub::ValidationMode::Strict)
+            // Don't forward messages until they have been validated with `report_gossip`
+            .validate_messages()
+            .message_id_fn(message_id)
+            .max_transmit_size(max_gossip_size)
+            .build()
+            .map_err(|err| Error::Gossip(err.to_string()))?;
+        let mut gossipsub = gossipsub::Behaviour::new(
+            gossipsub::MessageAuthenticity::Signed(keypair.clone()),
+            gossipsub_config,
+        )
+        .map_err(|err| Error::Gossip(err.to_string()))?;
+        for topic in NetworkEvent::TOPICS {
+            gossipsub
+                .subscribe(&gossipsub::IdentTopic::new(*topic))
+                .map_err(|err| Error::Gossip(err.to_string()))?;
+        }
+        // Gossip flood control is left to gossipsub's peer scoring, which penalizes the peers
+        // that forward messages we reject
+        gossipsub
+            .with_peer_score(gossip_score_params::<NetworkEvent>(), Default::default())
+            .map_err(Error::Gossip)?;
         let mut swarm = {
-            let whitelist = {
-                if whitelisted_ips.is_empty() {
-                    None
-                } else {
-                    let mut whitelist_config = whitelist_ips::Config::default();
-                    whitelist_config.whitelisted_ips = whitelisted_ips;
-                    let whitelist_ips = whitelist_ips::Behaviour::new_with_config(whitelist_config);
-                    Some(whitelist_ips)
-                }
-            }
-            .into();
+            let whitelist = whitelist_ips::Behaviour::new_with_config(ip_filter(
+                config.whitelisted_ips.clone(),
+                config.denied_ips.clone(),
+            ));
 
             let behaviour = Behaviour {
                 rr: request_response::Behaviour::new(
@@ -70,34 +115,52 @@ where
                     protocols,
                     rr_config,
                 ),
+                gossipsub,
+                identify: identify::Behaviour::new(identify::Config::new(
+                    "/polybase/0.1.0".to_string(),
+                    keypair.public(),
+                )),
                 keep_alive: keep_alive::Behaviour,
                 whitelist,
+                ban: Default::default(),
             };
             SwarmBuilder::with_tokio_executor(transport, behaviour, local_peer_id).build()
         };
 
-        // Listen on given addresses
-        for addr in listenaddrs {
-            swarm.listen_on(addr)?;
-        }
+        swarm.listen_on(config.laddr.clone())?;
 
-        // Connect to peers
-        for addr in dialaddrs {
-            info!(addr = ?addr, "Dialing peer");
-            swarm.dial(addr)?;
-        }
+        // The `dial` addresses are dialed on the first redial tick, and redialed along with
+        // the peers in the address book whenever we have fewer than `target_peers`
+        let mut address_book = AddressBook::load(
+            address_book_path,
+            config.dial.clone(),
+            Duration::from_secs(config.redial_backoff_min_secs),
+            Duration::from_secs(config.redial_backoff_max_secs),
+        );
+        let target_peers = config.target_peers;
+
+        let peer_scores = PeerScores::new(
+            config.rate_limits.clone(),
+            config.ban_threshold,
+            Duration::from_secs(config.ban_duration_secs),
+        );
 
         // Channel to receive NetworkEvents from the network
-        let (netin_tx, netin_rx) = mpsc::unbounded_channel::<(PeerId, NetworkEvent)>();
+        let (netin_tx, netin_rx) =
+            mpsc::unbounded_channel::<(PeerId, NetworkEvent, Option<GossipId>)>();
         let (netout_tx, mut netout_rx) = mpsc::unbounded_channel::<Command<NetworkEvent>>();
+        let (disconnected_tx, disconnected_rx) = mpsc::unbounded_channel::<PeerId>();
 
         // Shared state between the network and the spawned network behaviour event loop
-        let shared: Arc<NetworkShared> = Arc::new(NetworkShared::new());
+        let shared: Arc<NetworkShared> = Arc::new(NetworkShared::new(peer_scores));
         let shared_clone = Arc::clone(&shared);
 
         tokio::spawn(async move {
             let shared = shared_clone;
             let mut requests = HashMap::new();
+            // Addresses of the peers we dialed, so connected bootstrap addresses aren't redialed
+            let mut dialed_addrs = HashMap::<PeerId, Multiaddr>::new();
+            let mut redial = tokio::time::interval(REDIAL_INTERVAL);
 
             // TODO: add cancel loop
             loop {
@@ -111,6 +174,66 @@ where
                             Command::Dial(peer_id, response) => {
                                 response.send(swarm.dial(peer_id)).ok();
                             }
+                            Command::Publish(topic, event) => {
+                                let request = Request::V1(event);
+                                let data = match request.to_bytes() {
+                                    Ok(data) => data,
+                                    Err(err) => {
+                                        error!(?err, topic, "Failed to serialize gossip message");
+                                        continue;
+                                    }
+                                };
+                                match swarm.behaviour_mut().gossipsub.publish(gossipsub::IdentTopic::new(topic), data) {
+                                    Ok(_) => {}
+                                    // We've already published the same event
+                                    Err(gossipsub::PublishError::Duplicate) => {}
+                                    Err(err) => {
+                                        // E.g. the message is too large, or none of our peers have subscribed to the
+                                        // topic yet, so we fall back to sending it to each connected peer
+                                        warn!(?err, topic, "Failed to publish gossip message, sending it to connected peers");
+                                        let peers = shared.state.lock().connected_peers.clone();
+                                        for peer in peers {
+                                            swarm.behaviour_mut().rr.send_request(&peer, request.clone());
+                                        }
+                                    }
+                                }
+                            }
+                            Command::ReportGossip(GossipId { message_id, propagation_source }, acceptance) => {
+                                if let Err(err) = swarm.behaviour_mut().gossipsub.report_message_validation_result(&message_id, &propagation_source, acceptance) {
+                                    error!(?err, ?message_id, "Failed to report gossip validation result");
+                                }
+                            }
+                            Command::ReportMisbehaviour(peer_id, misbehaviour) => {
+                                penalize(&mut swarm, &mut shared.peer_scores.lock(), peer_id, misbehaviour);
+                            }
+                            Command::SetIpFilter(ip_filter) => {
+                                if swarm.behaviour().whitelist.config() != &ip_filter {
+                                    info!(whitelisted_ips = ?ip_filter.whitelisted_ips, denied_ips = ?ip_filter.denied_ips, "Updating IP filter");
+                                    swarm.behaviour_mut().whitelist.set_config(ip_filter);
+                                }
+                            }
+                        }
+                    }
+                    _ = redial.tick() => {
+                        shared.peer_scores.lock().prune(Instant::now());
+
+                        let connected = shared.state.lock().connected_peers.clone();
+                        let due = address_book.due(
+                            Instant::now(),
+                            |peer_id| connected.contains(peer_id) || swarm.behaviour().ban.is_banned(peer_id),
+                            |addr| dialed_addrs.values().any(|dialed| dialed == addr),
+                            target_peers.saturating_sub(connected.len()),
+                        );
+
+                        for dial in due {
+                            info!(?dial, "Dialing peer");
+                            let res = match dial {
+                                Dial::Peer(peer_id, addresses) => swarm.dial(DialOpts::peer_id(peer_id).addresses(addresses).build()),
+                                Dial::Address(addr) => swarm.dial(addr),
+                            };
+                            if let Err(err) = res {
+                                debug!(?err, "Failed to dial peer");
+                            }
                         }
                     }
                     event = swarm.select_next_some() => match event {
@@ -120,13 +243,31 @@ where
                         SwarmEvent::Dialing(peer_id) => {
                             info!(peer_id = ?peer_id, "Dialing peer");
                         }
-                        SwarmEvent::ConnectionEstablished { peer_id, established_in, .. } => {
+                        SwarmEvent::ConnectionEstablished { peer_id, endpoint, established_in, num_established, .. } => {
                             info!(peer_id = ?peer_id, established_in = ?established_in, "Connection established");
                             shared.add_peer(peer_id);
+
+                            let dialed_addr = match endpoint {
+                                ConnectedPoint::Dialer { address, .. } => Some(address),
+                                ConnectedPoint::Listener { .. } => None,
+                            };
+                            address_book.connected(&peer_id, dialed_addr.as_ref());
+                            if let Some(addr) = dialed_addr {
+                                dialed_addrs.insert(peer_id, addr);
+                            }
+
+                            if let Some(event) = on_connect.as_ref().filter(|_| num_established.get() == 1) {
+                                swarm.behaviour_mut().rr.send_request(&peer_id, Request::V1(event.clone()));
+                            }
                         }
                         SwarmEvent::ConnectionClosed { peer_id, endpoint, num_established, cause } => {
                             info!(peer_id = ?peer_id, num_established = num_established, endpoint = ?endpoint, cause = ?cause, "Connection closed");
-                            shared.remove_peer(&peer_id);
+                            if num_established == 0 {
+                                shared.remove_peer(&peer_id);
+                                dialed_addrs.remove(&peer_id);
+                                shared.peer_scores.lock().remove_peer(&peer_id);
+                                disconnected_tx.send(peer_id).ok();
+                            }
                         }
                         SwarmEvent::IncomingConnection { local_addr, send_back_addr } => {
                             info!(local_addr = ?local_addr, send_back_addr = ?send_back_addr, "Incoming connection");
@@ -152,11 +293,11 @@ where
                                     }
                                 },
                                 request_response::Message::Request{ request: Request::V1(request), channel, .. } => {
-                                        match netin_tx.send((peer, request)) {
-                                            Ok(_) => {},
-                                            Err(err) => {
-                                                error!(?err, peer_id = ?peer, "Failed to send, dropping event");
-                                            }
+                                        if !shared.peer_scores.lock().allow(peer, request.kind(), Instant::now()) {
+                                            debug!(peer_id = ?peer, kind = request.kind(), "Rate limited, dropping event");
+                                            penalize(&mut swarm, &mut shared.peer_scores.lock(), peer, Misbehaviour::RateLimited);
+                                        } else if let Err(err) = netin_tx.send((peer, request, None)) {
+                                            error!(?err, peer_id = ?peer, "Failed to send, dropping event");
                                         }
                                         match swarm.behaviour_mut().rr.send_response(channel, Response::V1) {
                                             Ok(_) => {},
@@ -168,6 +309,40 @@ where
                            }
                         }
                         SwarmEvent::Behaviour(BehaviourEvent::Rr(request_response::Event::ResponseSent { .. })) => {}
+                        SwarmEvent::Behaviour(BehaviourEvent::Identify(identify::Event::Received { peer_id, info })) => {
+                            address_book.add_addresses(peer_id, info.listen_addrs);
+                        }
+                        SwarmEvent::Behaviour(BehaviourEvent::Gossipsub(gossipsub::Event::Message { propagation_source, message_id, message })) => {
+                            let Ok(Request::V1(event)) = Request::<NetworkEvent>::from_bytes(&message.data) else {
+                                warn!(peer_id = ?propagation_source, ?message_id, "Invalid gossip message");
+                                if let Err(err) = swarm.behaviour_mut().gossipsub.report_message_validation_result(&message_id, &propagation_source, MessageAcceptance::Reject) {
+                                    error!(?err, ?message_id, "Failed to report gossip validation result");
+                                }
+                                penalize(&mut swarm, &mut shared.peer_scores.lock(), propagation_source, Misbehaviour::InvalidMessage);
+                                continue;
+                            };
+
+                            // Rate limits apply to the peer that forwarded the message to us, as
+                            // that's the connection it arrived on. The peer isn't penalized, as it
+                            // may just be forwarding a flood from another peer, which gossipsub's
+                            // peer scoring takes care of
+                            if !shared.peer_scores.lock().allow(propagation_source, event.kind(), Instant::now()) {
+                                debug!(peer_id = ?propagation_source, kind = event.kind(), "Rate limited, dropping gossip");
+                                if let Err(err) = swarm.behaviour_mut().gossipsub.report_message_validation_result(&message_id, &propagation_source, MessageAcceptance::Ignore) {
+                                    error!(?err, ?message_id, "Failed to report gossip validation result");
+                                }
+                                continue;
+                            }
+
+                            // Messages are signed, so the source is the peer that published it,
+                            // rather than the peer that forwarded it to us
+                            let peer = message.source.unwrap_or(propagation_source);
+
+                            let gossip_id = GossipId { message_id, propagation_source };
+                            if let Err(err) = netin_tx.send((peer, event, Some(gossip_id))) {
+                                error!(?err, peer_id = ?peer, "Failed to send, dropping event");
+                            }
+                        }
                         event => {
                             debug!(event = ?event, "Swarm event");
                         }
@@ -178,6 +353,7 @@ where
 
         Ok(Network {
             netin_rx: AsyncMutex::new(netin_rx),
+            disconnected_rx: AsyncMutex::new(disconnected_rx),
             netout_tx,
             local_peer_id,
             shared,
@@ -202,7 +378,27 @@ where
         self._send(peer, event).await;
     }
 
+    pub fn connected_peers(&self) -> Vec<PeerId> {
+        self.shared
+            .state
+            .lock()
+            .connected_peers
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    /// Send an event to every peer. Events with a [`Gossip::topic`] are published to the topic,
+    /// so they are also forwarded to peers we're not connected to. If publishing fails, they are
+    /// sent to each connected peer instead
     pub async fn send_all(&self, event: NetworkEvent) {
+        if let Some(topic) = event.topic() {
+            if let Err(err) = self.netout_tx.send(Command::Publish(topic, event)) {
+                error!(?err, topic, "Failed to publish, dropping event");
+            }
+            return;
+        }
+
         let peers = self.shared.state.lock().connected_peers.clone();
         let mut futures = vec![];
 
@@ -231,21 +427,104 @@ where
         Some(rx)
     }
 
-    pub async fn next(&self) -> Option<(PeerId, NetworkEvent)> {
+    /// The next event received from a peer. Gossip events have a [`GossipId`], and are only
+    /// forwarded to other peers once they've been accepted with [`Self::report_gossip`]
+    pub async fn next(&self) -> Option<(PeerId, NetworkEvent, Option<GossipId>)> {
         self.netin_rx.lock().await.recv().await
     }
+
+    /// The next peer to disconnect, once all of its connections have closed
+    pub async fn next_disconnected(&self) -> Option<PeerId> {
+        self.disconnected_rx.lock().await.recv().await
+    }
+
+    /// The peer's misbehaviour score, 0 for a peer that hasn't misbehaved recently. Peers are
+    /// banned once their score reaches the ban threshold
+    pub fn peer_score(&self, peer: &PeerId) -> f64 {
+        self.shared.peer_scores.lock().score(peer, Instant::now())
+    }
+
+    /// Report misbehaviour by a peer, which is banned if its score reaches the ban threshold
+    pub fn report_misbehaviour(&self, peer: PeerId, misbehaviour: Misbehaviour) -> Result<()> {
+        self.netout_tx
+            .send(Command::ReportMisbehaviour(peer, misbehaviour))
+            .map_err(|err| Error::ChannelError(err.to_string()))
+    }
+
+    /// Replace the IP allow and deny lists. Connections that are no longer allowed are closed
+    pub fn set_ip_filter(
+        &self,
+        whitelisted_ips: HashSet<IpNet>,
+        denied_ips: HashSet<IpNet>,
+    ) -> Result<()> {
+        self.netout_tx
+            .send(Command::SetIpFilter(ip_filter(whitelisted_ips, denied_ips)))
+            .map_err(|err| Error::ChannelError(err.to_string()))
+    }
+
+    /// Report whether a gossip event is valid. Accepted events are forwarded to other peers,
+    /// rejected events count against the peer that forwarded them
+    pub fn report_gossip(&self, id: GossipId, acceptance: MessageAcceptance) -> Result<()> {
+        self.netout_tx
+            .send(Command::ReportGossip(id, acceptance))
+            .map_err(|err| Error::ChannelError(err.to_string()))
+    }
+}
+
+fn ip_filter(whitelisted_ips: HashSet<IpNet>, denied_ips: HashSet<IpNet>) -> whitelist_ips::Config {
+    let mut config = whitelist_ips::Config::default();
+    config.whitelisted_ips = whitelisted_ips;
+    config.denied_ips = denied_ips;
+    config
+}
+
+/// Gossipsub peer scoring params, so peers that forward invalid messages are pruned from the
+/// mesh and eventually ignored
+fn gossip_score_params<NetworkEvent: Gossip>() -> gossipsub::PeerScoreParams {
+    let mut params = gossipsub::PeerScoreParams::default();
+    for topic in NetworkEvent::TOPICS {
+        params.topics.insert(
+            gossipsub::IdentTopic::new(*topic).hash(),
+            gossipsub::TopicScoreParams {
+                // Some topics are quiet (e.g. approvals are only sent once per block), so peers
+                // aren't penalized for delivering too few messages
+                mesh_message_deliveries_weight: 0.0,
+                mesh_failure_penalty_weight: 0.0,
+                ..Default::default()
+            },
+        );
+    }
+    params
+}
+
+/// Add `misbehaviour` to the peer's score, and ban it if the score reaches the ban threshold
+fn penalize<NetworkEvent>(
+    swarm: &mut Swarm<Behaviour<NetworkEvent>>,
+    peer_scores: &mut PeerScores,
+    peer: PeerId,
+    misbehaviour: Misbehaviour,
+) where
+    NetworkEvent: Clone + Sync + Send + BorshSerialize + BorshDeserialize + 'static,
+{
+    if let Some(until) = peer_scores.penalize(peer, misbehaviour, Instant::now()) {
+        warn!(peer_id = ?peer, ?misbehaviour, "Banning peer");
+        swarm.behaviour_mut().ban.ban(peer, until);
+    }
 }
 
 struct NetworkShared {
     state: Mutex<NetworkSharedState>,
+    /// Rate limits and misbehaviour scores, shared so the node can prefer well behaved peers
+    peer_scores: Mutex<PeerScores>,
 }
 
 impl NetworkShared {
-    fn new() -> NetworkShared {
+    fn new(peer_scores: PeerScores) -> NetworkShared {
         NetworkShared {
             state: Mutex::new(NetworkSharedState {
                 connected_peers: HashSet::new(),
             }),
+            peer_scores: Mutex::new(peer_scores),
         }
     }
 
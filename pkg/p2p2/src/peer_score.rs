@@ -0,0 +1,218 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use libp2p::PeerId;
+
+/// Network events, grouped into kinds so each kind can be rate limited separately
+pub trait MessageKind {
+    /// The kind of this event, as used in the `rate-limits` config
+    fn kind(&self) -> &'static str;
+}
+
+/// Misbehaviour by a peer, which adds to the peer's score. Peers are banned once their score
+/// reaches the ban threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Misbehaviour {
+    /// Sent more messages of a kind than its rate limit allows
+    RateLimited,
+    /// Sent a message that couldn't be decoded or failed validation
+    InvalidMessage,
+    /// Sent a txn with an invalid proof
+    InvalidProof,
+    /// Sent a block that failed validation
+    InvalidBlock,
+    /// Sent a snapshot chunk we didn't ask for, or that couldn't be applied
+    BadSnapshotChunk,
+    /// Didn't send a snapshot chunk it offered in time
+    SnapshotTimeout,
+}
+
+impl Misbehaviour {
+    /// Penalties are below the default ban threshold of 100, so a peer is only banned for
+    /// repeated misbehaviour
+    fn penalty(self) -> f64 {
+        match self {
+            Misbehaviour::RateLimited => 5.0,
+            Misbehaviour::InvalidMessage => 20.0,
+            Misbehaviour::InvalidProof => 50.0,
+            Misbehaviour::InvalidBlock => 60.0,
+            Misbehaviour::BadSnapshotChunk => 50.0,
+            Misbehaviour::SnapshotTimeout => 5.0,
+        }
+    }
+}
+
+/// Scores decay by this much each second, so occasional misbehaviour is forgiven
+const SCORE_DECAY_PER_SEC: f64 = 1.0;
+
+/// Per-peer rate limits and misbehaviour scores
+pub(crate) struct PeerScores {
+    /// Messages per second each peer can send, by message kind
+    rate_limits: HashMap<String, u32>,
+    ban_threshold: f64,
+    ban_duration: Duration,
+    buckets: HashMap<(PeerId, &'static str), Bucket>,
+    scores: HashMap<PeerId, Score>,
+}
+
+/// A token bucket holding up to a second of messages
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+struct Score {
+    score: f64,
+    updated: Instant,
+}
+
+impl Score {
+    fn decayed(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        (self.score - elapsed * SCORE_DECAY_PER_SEC).max(0.0)
+    }
+}
+
+impl PeerScores {
+    pub(crate) fn new(
+        rate_limits: HashMap<String, u32>,
+        ban_threshold: u32,
+        ban_duration: Duration,
+    ) -> Self {
+        Self {
+            rate_limits,
+            ban_threshold: f64::from(ban_threshold),
+            ban_duration,
+            buckets: HashMap::new(),
+            scores: HashMap::new(),
+        }
+    }
+
+    /// Whether `peer` can send another message of `kind`. Kinds without a rate limit are
+    /// always allowed
+    pub(crate) fn allow(&mut self, peer: PeerId, kind: &'static str, now: Instant) -> bool {
+        let Some(&limit) = self.rate_limits.get(kind) else {
+            return true;
+        };
+        let limit = f64::from(limit);
+
+        let bucket = self.buckets.entry((peer, kind)).or_insert(Bucket {
+            tokens: limit,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit).min(limit);
+        bucket.updated = now;
+
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    /// Add `misbehaviour` to the peer's score. Returns when the ban ends if the peer should now
+    /// be banned
+    pub(crate) fn penalize(
+        &mut self,
+        peer: PeerId,
+        misbehaviour: Misbehaviour,
+        now: Instant,
+    ) -> Option<Instant> {
+        let score = self.scores.entry(peer).or_insert(Score {
+            score: 0.0,
+            updated: now,
+        });
+        score.score = score.decayed(now) + misbehaviour.penalty();
+        score.updated = now;
+
+        if score.score < self.ban_threshold {
+            return None;
+        }
+
+        // The ban is the punishment, the peer starts again afterwards
+        self.scores.remove(&peer);
+        Some(now + self.ban_duration)
+    }
+
+    /// The peer's current score, 0 for a peer that hasn't misbehaved recently
+    pub(crate) fn score(&self, peer: &PeerId, now: Instant) -> f64 {
+        self.scores
+            .get(peer)
+            .map_or(0.0, |score| score.decayed(now))
+    }
+
+    /// Forget the rate limits of a disconnected peer. Its score is kept, so it can't be reset
+    /// by reconnecting
+    pub(crate) fn remove_peer(&mut self, peer: &PeerId) {
+        self.buckets
+            .retain(|(bucket_peer, _), _| bucket_peer != peer);
+    }
+
+    /// Forget scores that have decayed to 0
+    pub(crate) fn prune(&mut self, now: Instant) {
+        self.scores.retain(|_, score| score.decayed(now) > 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scores() -> PeerScores {
+        let rate_limits = [("txn".to_string(), 2)].into_iter().collect();
+        PeerScores::new(rate_limits, 100, Duration::from_secs(60))
+    }
+
+    #[test]
+    fn rate_limit() {
+        let mut scores = scores();
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        assert!(scores.allow(peer, "txn", now));
+        assert!(scores.allow(peer, "txn", now));
+        assert!(!scores.allow(peer, "txn", now));
+
+        // Limits are per peer and kind
+        assert!(scores.allow(PeerId::random(), "txn", now));
+        assert!(scores.allow(peer, "block", now));
+
+        // Refilled at the rate limit
+        assert!(scores.allow(peer, "txn", now + Duration::from_millis(500)));
+        assert!(!scores.allow(peer, "txn", now + Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn ban_once_threshold_reached() {
+        let mut scores = scores();
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        assert_eq!(scores.penalize(peer, Misbehaviour::InvalidProof, now), None);
+        assert_eq!(scores.score(&peer, now), 50.0);
+        assert_eq!(scores.score(&peer, now + Duration::from_secs(10)), 40.0);
+
+        // The score decays, so the same misbehaviour much later doesn't get the peer banned
+        let later = now + Duration::from_secs(60);
+        assert_eq!(
+            scores.penalize(peer, Misbehaviour::InvalidProof, later),
+            None
+        );
+        assert_eq!(
+            scores.penalize(peer, Misbehaviour::InvalidProof, later),
+            Some(later + Duration::from_secs(60))
+        );
+
+        // Scores are reset by the ban
+        assert_eq!(
+            scores.penalize(peer, Misbehaviour::RateLimited, later),
+            None
+        );
+        scores.prune(later + Duration::from_secs(10));
+        assert!(scores.scores.is_empty());
+    }
+}
@@ -101,6 +101,16 @@ impl HTTPError {
             .map(|s| s.to_string())
             .unwrap_or_default()
     }
+
+    /// The details of the error, as returned in the body of an error response
+    pub fn detail(&self) -> ErrorDetail {
+        ErrorDetail {
+            code: self.code.to_string(),
+            reason: self.reason.clone(),
+            message: self.message(),
+            data: self.data.clone(),
+        }
+    }
 }
 
 impl Display for HTTPError {
@@ -118,12 +128,7 @@ impl std::error::Error for HTTPError {
 impl actix_web::error::ResponseError for HTTPError {
     fn error_response(&self) -> HttpResponse {
         let error = ErrorOutput {
-            error: ErrorDetail {
-                code: self.code.to_string(),
-                reason: self.reason.clone(),
-                message: self.message(),
-                data: self.data.clone(),
-            },
+            error: self.detail(),
         };
         #[allow(clippy::unwrap_used)]
         HttpResponse::build(self.status_code())
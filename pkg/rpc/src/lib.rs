@@ -1,7 +1,9 @@
 #![warn(clippy::unwrap_used, clippy::expect_used)]
+#![feature(once_cell)] // this feature is fine beause it's since been stabilized
 #![deny(clippy::disallowed_methods)]
 pub mod code;
 pub mod error;
 pub mod longpoll;
+pub mod metrics;
 pub mod middleware;
 pub mod tracing;
@@ -0,0 +1,41 @@
+use actix_web::{HttpResponse, Responder};
+use opentelemetry_prometheus::PrometheusExporter;
+use prometheus::{Encoder, Registry, TextEncoder};
+use std::sync::OnceLock;
+
+/// Registry for metrics served in the Prometheus text format. Every instrument created with
+/// [`crate::tracing::meter`] (and every `counter.*`/`histogram.*` tracing field) is collected
+/// into it once [`crate::tracing::setup_tracing`] has been called
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(Registry::new)
+}
+
+/// A metric reader which exports to [`registry`]
+pub(crate) fn prometheus_exporter() -> eyre::Result<PrometheusExporter> {
+    Ok(opentelemetry_prometheus::exporter()
+        .with_registry(registry().clone())
+        .build()?)
+}
+
+/// Encode the current value of every metric in the Prometheus text format
+pub fn encode() -> eyre::Result<String> {
+    let encoder = TextEncoder::new();
+    let mut buf = Vec::new();
+    encoder.encode(&registry().gather(), &mut buf)?;
+
+    Ok(String::from_utf8(buf)?)
+}
+
+/// Actix handler for a Prometheus `/metrics` endpoint
+pub async fn metrics() -> impl Responder {
+    match encode() {
+        Ok(body) => HttpResponse::Ok()
+            .content_type(TextEncoder::new().format_type())
+            .body(body),
+        Err(err) => {
+            tracing::error!(?err, "Failed to encode metrics");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
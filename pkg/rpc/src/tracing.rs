@@ -3,7 +3,13 @@ use opentelemetry_otlp::{
     OTEL_EXPORTER_OTLP_ENDPOINT, OTEL_EXPORTER_OTLP_METRICS_ENDPOINT,
     OTEL_EXPORTER_OTLP_TRACES_ENDPOINT,
 };
-use opentelemetry_sdk::trace::{RandomIdGenerator, Sampler};
+use opentelemetry_sdk::{
+    metrics::{
+        reader::{DefaultAggregationSelector, DefaultTemporalitySelector},
+        PeriodicReader, SdkMeterProvider,
+    },
+    trace::{RandomIdGenerator, Sampler},
+};
 use sentry_tracing::{event_from_event, EventMapping};
 use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, fmt};
@@ -124,17 +130,25 @@ pub fn setup_tracing(
 
     let use_otel_metrics = std::env::var(OTEL_EXPORTER_OTLP_ENDPOINT).is_ok()
         || std::env::var(OTEL_EXPORTER_OTLP_METRICS_ENDPOINT).is_ok();
-    let metrics = if use_otel_metrics {
-        let meter_provider = opentelemetry_otlp::new_pipeline()
-            .metrics(opentelemetry_sdk::runtime::Tokio)
-            .with_exporter(opentelemetry_otlp::new_exporter().tonic())
-            .build()?;
+    // Metrics are always served at `/metrics`, and also pushed to the collector if configured
+    let mut meter_provider =
+        SdkMeterProvider::builder().with_reader(crate::metrics::prometheus_exporter()?);
+    if use_otel_metrics {
+        let exporter = opentelemetry_otlp::new_exporter()
+            .tonic()
+            .build_metrics_exporter(
+                Box::new(DefaultAggregationSelector::new()),
+                Box::new(DefaultTemporalitySelector::new()),
+            )?;
+
+        meter_provider = meter_provider.with_reader(
+            PeriodicReader::builder(exporter, opentelemetry_sdk::runtime::Tokio).build(),
+        );
+    }
+    let meter_provider = meter_provider.build();
 
-        opentelemetry::global::set_meter_provider(meter_provider.clone());
-        Some(tracing_opentelemetry::MetricsLayer::new(meter_provider))
-    } else {
-        None
-    };
+    opentelemetry::global::set_meter_provider(meter_provider.clone());
+    let metrics = tracing_opentelemetry::MetricsLayer::new(meter_provider);
 
     let sentry_layer = sentry_tracing::layer().event_mapper(|evt, ctx| {
         let mut sentry_visitor = SentryEventVisitor {
@@ -124,9 +124,37 @@ pub fn storage_load(b: &mut BenchmarkRun) {
     );
 }
 
+#[benchmark]
+pub fn storage_load_from_checkpoint(b: &mut BenchmarkRun) {
+    let dir = TempDir::new("smirk-benchmark").unwrap();
+    let checkpoint = dir.path().join("checkpoint");
+
+    let batch = make_batch(1000);
+
+    let mut persistent = Persistent::<160, ()>::new(dir.path().join("db")).unwrap();
+    persistent.insert_batch(batch).unwrap();
+    persistent.write_checkpoint(&checkpoint, 1).unwrap();
+    drop(persistent);
+
+    b.run(|| {
+        let tree = Persistent::<160, ()>::load_from_checkpoint(dir.path().join("db"), &checkpoint)
+            .unwrap();
+        black_box(tree);
+    });
+
+    b.metrics
+        .insert("hash_count".into(), zk_primitives::hash_count());
+
+    b.metrics.insert(
+        "hash_element_count".into(),
+        zk_primitives::hash_element_count(),
+    );
+}
+
 benchy::main!(
     // hash_merge_1_000_000,
     // hash_merge_1_000_000_cached,
     create_tree,
     storage_load,
+    storage_load_from_checkpoint,
 );
@@ -80,5 +80,5 @@ mod tree;
 
 pub use batch::Batch;
 pub use hash::empty_tree_hash;
-pub use tree::{Collision, CollisionError, Path, Tree};
+pub use tree::{Collision, CollisionError, ExclusionProof, Path, Tree};
 pub use zk_primitives::*;
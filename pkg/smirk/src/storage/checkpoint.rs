@@ -0,0 +1,204 @@
+use core::fmt::Debug;
+use std::path::Path;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use rocksdb::DB;
+use wire_message::{wire_message, WireMessage};
+use zk_primitives::Element;
+
+use crate::{
+    hash_cache::{KnownHash, SimpleHashCache},
+    Batch, Tree,
+};
+
+use super::{Error, Persistent};
+
+/// A snapshot of a [`Tree`] that can be loaded without replaying every rocksdb entry
+#[derive(Debug, Clone)]
+#[wire_message]
+enum CheckpointFormat<V: Clone> {
+    V1(CheckpointV1<V>),
+}
+
+impl<V> WireMessage for CheckpointFormat<V>
+where
+    V: Clone + BorshSerialize + BorshDeserialize + Send + Sync + 'static,
+{
+    type Ctx = ();
+    type Err = core::convert::Infallible;
+
+    fn version(&self) -> u64 {
+        match self {
+            Self::V1(_) => 1,
+        }
+    }
+
+    fn upgrade_once(self, _ctx: &mut Self::Ctx) -> Result<Self, wire_message::Error> {
+        match self {
+            Self::V1(_) => Err(Self::max_version_error()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+struct CheckpointV1<V: Clone> {
+    /// The root hash of the tree when the checkpoint was written
+    root_hash: Element,
+    /// The height the tree was at when the checkpoint was written, as passed to
+    /// [`Persistent::write_checkpoint`]
+    height: u64,
+    /// Every element in the tree, in ascending order
+    entries: Vec<(Element, V)>,
+    /// `(left, right, result)` for each [`KnownHash`] in the tree
+    known_hashes: Vec<(Element, Element, Element)>,
+}
+
+/// A snapshot of a [`Persistent`] [`Tree`] taken by [`Persistent::checkpoint`], which can be
+/// written to a file after the tree has been released
+#[derive(Debug, Clone)]
+pub struct Checkpoint<V: Clone>(CheckpointFormat<V>);
+
+impl<V> Checkpoint<V>
+where
+    V: BorshSerialize + BorshDeserialize + Send + Sync + 'static + Clone,
+{
+    /// Write the checkpoint to `path`, see [`Persistent::write_checkpoint`]
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, self.0.to_bytes()?)?;
+        std::fs::rename(&tmp_path, path)?;
+
+        Ok(())
+    }
+}
+
+impl<const DEPTH: usize, V> Persistent<DEPTH, V> {
+    /// Take a [`Checkpoint`] of the in-memory tree at `height`, to be written with
+    /// [`Checkpoint::write`]. Copies the tree's elements and known hashes, but doesn't serialize
+    /// them, so a large tree can be written without holding on to it
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use smirk::storage::*;
+    /// # let dir = tempdir::TempDir::new("smirk_doctest").unwrap();
+    /// # let path = dir.path().join("db");
+    /// # let checkpoint_path = dir.path().join("checkpoint");
+    /// let mut persistent = Persistent::<64, i32>::new(&path).unwrap();
+    /// persistent.insert(Element::ONE, 123).unwrap();
+    ///
+    /// let checkpoint = persistent.checkpoint(1);
+    /// drop(persistent);
+    /// checkpoint.write(&checkpoint_path).unwrap();
+    /// ```
+    pub fn checkpoint(&self, height: u64) -> Checkpoint<V>
+    where
+        V: Clone,
+    {
+        Checkpoint(CheckpointFormat::V1(CheckpointV1 {
+            root_hash: self.tree.root_hash(),
+            height,
+            entries: self
+                .tree
+                .iter()
+                .map(|(element, value)| (*element, value.clone()))
+                .collect(),
+            known_hashes: self
+                .tree
+                .known_hashes()
+                .into_iter()
+                .map(|hash| (hash.left, hash.right, hash.result))
+                .collect(),
+        }))
+    }
+
+    /// Write a checkpoint of the in-memory tree to `path`, which can be used to speed up
+    /// [`Persistent::load_from_checkpoint`]. `height` is stored with the checkpoint, so the
+    /// caller knows which changes to apply after loading it
+    ///
+    /// The file is written to a temporary path first and then renamed, so an existing checkpoint
+    /// at `path` is never left half-written
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use smirk::storage::*;
+    /// # let dir = tempdir::TempDir::new("smirk_doctest").unwrap();
+    /// # let path = dir.path().join("db");
+    /// # let checkpoint = dir.path().join("checkpoint");
+    /// let mut persistent = Persistent::<64, i32>::new(&path).unwrap();
+    /// persistent.insert(Element::ONE, 123).unwrap();
+    ///
+    /// persistent.write_checkpoint(&checkpoint, 1).unwrap();
+    /// ```
+    pub fn write_checkpoint<P: AsRef<Path>>(&self, path: P, height: u64) -> Result<(), Error>
+    where
+        V: BorshSerialize + BorshDeserialize + Send + Sync + 'static + Clone,
+    {
+        self.checkpoint(height).write(path)
+    }
+
+    /// Load a [`Persistent`] [`Tree`] from the checkpoint at `checkpoint`, backed by the rocksdb
+    /// database at `path`. Also returns the height the checkpoint was written at
+    ///
+    /// The rebuilt tree must have the root hash recorded in the checkpoint, otherwise
+    /// [`Error::CheckpointRootMismatch`] is returned. Elements are not read from the database,
+    /// so the caller must make sure the checkpoint is not behind it (for example, by comparing
+    /// the root hash with a known root and inserting any missing elements)
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use smirk::storage::*;
+    /// # let dir = tempdir::TempDir::new("smirk_doctest").unwrap();
+    /// # let path = dir.path().join("db");
+    /// # let checkpoint = dir.path().join("checkpoint");
+    /// let mut persistent = Persistent::<64, i32>::new(&path).unwrap();
+    /// persistent.insert(Element::ONE, 123).unwrap();
+    /// persistent.write_checkpoint(&checkpoint, 1).unwrap();
+    ///
+    /// drop(persistent);
+    ///
+    /// let (persistent, height) =
+    ///     Persistent::<64, i32>::load_from_checkpoint(&path, &checkpoint).unwrap();
+    /// assert_eq!(persistent.tree().get(Element::ONE), Some(&123));
+    /// assert_eq!(height, 1);
+    /// ```
+    pub fn load_from_checkpoint<P: AsRef<Path>, Q: AsRef<Path>>(
+        path: P,
+        checkpoint: Q,
+    ) -> Result<(Self, u64), Error>
+    where
+        V: BorshDeserialize + BorshSerialize + Debug + Clone + Send + Sync + 'static,
+    {
+        let bytes = std::fs::read(checkpoint)?;
+        let CheckpointFormat::V1(checkpoint) = CheckpointFormat::<V>::from_bytes(&bytes)?;
+
+        let mut cache = SimpleHashCache::new();
+        cache.provide_known_hashes(
+            checkpoint
+                .known_hashes
+                .into_iter()
+                .map(|(left, right, result)| KnownHash {
+                    left,
+                    right,
+                    result,
+                })
+                .collect(),
+        );
+
+        let mut tree = Tree::<DEPTH, V, SimpleHashCache>::new_with_cache(cache);
+        tree.insert_batch(Batch::from_entries(checkpoint.entries)?, |_| {}, |_| {})?;
+
+        let root_hash = tree.root_hash();
+        if root_hash != checkpoint.root_hash {
+            return Err(Error::CheckpointRootMismatch {
+                expected: checkpoint.root_hash,
+                got: root_hash,
+            });
+        }
+
+        let db = DB::open_default(path)?;
+
+        Ok((Self { tree, db }, checkpoint.height))
+    }
+}
@@ -1,3 +1,5 @@
+use zk_primitives::Element;
+
 use crate::CollisionError;
 
 /// An error that can occur when interacting with a a [`Persistent`]
@@ -29,4 +31,17 @@ pub enum Error {
     /// Database consistency
     #[error("the database contained inconsistent data")]
     DatabaseConsistency,
+
+    /// An error reading or writing a checkpoint file
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// A checkpoint didn't rebuild to the root hash it was written with
+    #[error("checkpoint root hash mismatch, expected: {expected}, got: {got}")]
+    CheckpointRootMismatch {
+        /// The root hash recorded in the checkpoint
+        expected: Element,
+        /// The root hash of the rebuilt tree
+        got: Element,
+    },
 }
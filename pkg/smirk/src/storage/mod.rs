@@ -4,11 +4,13 @@ use std::path::Path;
 use borsh::{BorshDeserialize, BorshSerialize};
 use rocksdb::DB;
 
+pub use checkpoint::Checkpoint;
 pub use error::Error;
 
-use crate::{hash_cache::SimpleHashCache, Element, Tree};
+use crate::{hash_cache::SimpleHashCache, Element, ExclusionProof, Tree};
 
 mod batch;
+mod checkpoint;
 mod error;
 mod format;
 mod load;
@@ -142,6 +144,29 @@ impl<const DEPTH: usize, V> Persistent<DEPTH, V> {
         self.insert_batch(crate::batch! { element => value })
     }
 
+    /// Generate an [`ExclusionProof`] that proves `element` is not in the tree
+    ///
+    /// This is equivalent to calling [`Tree::exclusion_proof`] on [`Persistent::tree`]
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// # use smirk::storage::*;
+    /// # let dir = tempdir::TempDir::new("smirk_doctest").unwrap();
+    /// # let path = dir.path().join("db");
+    /// let mut persistent = Persistent::<64, ()>::new(&path).unwrap();
+    /// persistent.insert(Element::new(1), ()).unwrap();
+    ///
+    /// let proof = persistent.exclusion_proof(Element::new(2)).unwrap();
+    /// assert!(proof.verify());
+    ///
+    /// assert!(persistent.exclusion_proof(Element::new(1)).is_none());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn exclusion_proof(&self, element: Element) -> Option<ExclusionProof<DEPTH>> {
+        self.tree.exclusion_proof(element)
+    }
+
     /// Store all computed hashes from the in-memory tree into rocksdb
     ///
     /// Note that this function is never called automatically when inserting. Make sure to call
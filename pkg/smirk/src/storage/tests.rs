@@ -154,3 +154,78 @@ fn insert_batch_hash_test() {
         hashes: expect_file!["test-snapshots/known_hashes_2.txt"]
     );
 }
+
+#[proptest(cases = cases())]
+fn checkpoint_round_trip(batch: Batch<64, i32>) {
+    let (dir, path) = setup_path();
+    let checkpoint = dir.path().join("checkpoint");
+
+    let mut persistent = Persistent::<64, i32>::new(&path).unwrap();
+    persistent.insert_batch(batch).unwrap();
+    persistent.write_checkpoint(&checkpoint, 7).unwrap();
+
+    let root_hash = persistent.tree().root_hash();
+    let entries = persistent
+        .tree()
+        .iter()
+        .map(|(k, v)| (*k, *v))
+        .collect::<Vec<_>>();
+
+    drop(persistent);
+
+    let (loaded, height) = Persistent::<64, i32>::load_from_checkpoint(&path, &checkpoint).unwrap();
+    assert_eq!(height, 7);
+    assert_eq!(loaded.tree().root_hash(), root_hash);
+    assert_eq!(
+        loaded
+            .tree()
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .collect::<Vec<_>>(),
+        entries
+    );
+}
+
+#[test]
+fn checkpoint_uses_known_hashes() {
+    let (dir, path) = setup_path();
+    let checkpoint = dir.path().join("checkpoint");
+
+    let mut persistent = Persistent::<64, ()>::new(&path).unwrap();
+    persistent.insert_batch(batch! { 2, 3 }).unwrap();
+    persistent.write_checkpoint(&checkpoint, 1).unwrap();
+
+    drop(persistent);
+
+    let (loaded, _) = Persistent::<64, ()>::load_from_checkpoint(&path, &checkpoint).unwrap();
+    let metrics = loaded.tree().cache().metrics();
+    assert!(metrics.hashes() > 0);
+    assert_eq!(metrics.cache_misses(), 0);
+}
+
+#[test]
+fn corrupt_checkpoint_is_rejected() {
+    let (dir, path) = setup_path();
+    let checkpoint = dir.path().join("checkpoint");
+
+    let mut persistent = Persistent::<64, ()>::new(&path).unwrap();
+    persistent.insert_batch(batch! { 2, 3 }).unwrap();
+    persistent.write_checkpoint(&checkpoint, 1).unwrap();
+
+    // the recorded root hash is the first field, so flip a bit in its first occurrence
+    let root_hash = persistent.tree().root_hash().to_be_bytes();
+    drop(persistent);
+
+    let mut bytes = std::fs::read(&checkpoint).unwrap();
+    let start = bytes
+        .windows(root_hash.len())
+        .position(|window| window == root_hash)
+        .unwrap();
+    bytes[start + 31] ^= 1;
+    std::fs::write(&checkpoint, bytes).unwrap();
+
+    let err = Persistent::<64, ()>::load_from_checkpoint(&path, &checkpoint)
+        .err()
+        .unwrap();
+    assert!(matches!(err, Error::CheckpointRootMismatch { .. }));
+}
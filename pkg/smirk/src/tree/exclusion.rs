@@ -0,0 +1,209 @@
+use crate::{Element, Path, Tree};
+
+use super::tree_repr::Node;
+
+/// A proof that an [`Element`] is *not* present in a [`Tree`] with a known root hash
+///
+/// Every [`Element`] has a "slot" in the tree, determined by its `DEPTH - 1` least significant
+/// bits. An [`ExclusionProof`] shows that this slot is either empty (i.e. contains
+/// [`Element::NULL_HASH`]), or is occupied by a different element with the same least
+/// significant bits.
+///
+/// To get an [`ExclusionProof`], generate it from a tree:
+/// ```rust
+/// # use smirk::*;
+/// let tree: Tree<64, _> = smirk! { 1, 2, 3 };
+///
+/// // 4 is not in the tree, so we can prove its absence
+/// let proof = tree.exclusion_proof(Element::new(4)).unwrap();
+/// assert_eq!(proof.element(), Element::new(4));
+/// assert_eq!(proof.occupant(), None);
+/// assert!(proof.verify());
+///
+/// // 1 is in the tree, so no exclusion proof exists
+/// assert!(tree.exclusion_proof(Element::new(1)).is_none());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExclusionProof<const DEPTH: usize> {
+    path: Path<DEPTH>,
+    /// The element currently in the slot, or [`Element::NULL_HASH`] if the slot is empty
+    occupant: Element,
+}
+
+impl<const DEPTH: usize> ExclusionProof<DEPTH> {
+    /// The [`Element`] that this proof proves the absence of
+    #[inline]
+    #[must_use]
+    pub fn element(&self) -> Element {
+        self.path.element()
+    }
+
+    /// The [`Element`] occupying the slot of [`Self::element`], or `None` if the slot is empty
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// let tree: Tree<64, _> = smirk! { 1 };
+    ///
+    /// // same least significant bits as 1, but different upper bits
+    /// let collides_with_1 = Element::ONE + (Element::ONE << 100);
+    ///
+    /// let proof = tree.exclusion_proof(collides_with_1).unwrap();
+    /// assert_eq!(proof.occupant(), Some(Element::ONE));
+    /// assert!(proof.verify());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn occupant(&self) -> Option<Element> {
+        match self.occupant == Element::NULL_HASH {
+            true => None,
+            false => Some(self.occupant),
+        }
+    }
+
+    /// The Merkle [`Path`] to the slot of [`Self::element`]
+    #[inline]
+    #[must_use]
+    pub fn path(&self) -> &Path<DEPTH> {
+        &self.path
+    }
+
+    /// The root hash of the tree when this proof was created
+    #[inline]
+    #[must_use]
+    pub fn actual_root_hash(&self) -> Element {
+        self.path.actual_root_hash()
+    }
+
+    /// Check that this proof is consistent with [`Self::actual_root_hash`]
+    ///
+    /// That is, the occupant of the slot is different to [`Self::element`], shares its least
+    /// significant bits (if the slot isn't empty), and the [`Path`] links the occupant to the
+    /// root hash.
+    ///
+    /// Note that this only proves absence from a tree with root hash [`Self::actual_root_hash`],
+    /// so a verifier must also check that this root hash is one they trust
+    #[must_use]
+    pub fn verify(&self) -> bool {
+        let element = self.element();
+
+        if element == Element::NULL_HASH || self.occupant == element {
+            return false;
+        }
+
+        if self.occupant != Element::NULL_HASH
+            && self.occupant.lsb(DEPTH - 1) != element.lsb(DEPTH - 1)
+        {
+            return false;
+        }
+
+        self.path.proves(self.occupant)
+    }
+}
+
+impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
+    /// Generate an [`ExclusionProof`] that proves `element` is not in the tree
+    ///
+    /// Returns `None` if `element` is in the tree, or is [`Element::NULL_HASH`] (which is
+    /// considered to be present in every empty slot)
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// let tree: Tree<64, _> = smirk! { 1, 2, 3 };
+    ///
+    /// let proof = tree.exclusion_proof(Element::new(4)).unwrap();
+    /// assert_eq!(proof.actual_root_hash(), tree.root_hash());
+    /// assert!(proof.verify());
+    ///
+    /// assert!(tree.exclusion_proof(Element::new(2)).is_none());
+    /// assert!(tree.exclusion_proof(Element::NULL_HASH).is_none());
+    /// ```
+    #[must_use]
+    pub fn exclusion_proof(&self, element: Element) -> Option<ExclusionProof<DEPTH>> {
+        if element == Element::NULL_HASH || self.contains_element(&element) {
+            return None;
+        }
+
+        Some(ExclusionProof {
+            path: self.path_for(element),
+            occupant: self.slot_occupant(element),
+        })
+    }
+
+    /// The element in the slot that `element` would occupy, or [`Element::NULL_HASH`] if the slot
+    /// is empty
+    fn slot_occupant(&self, element: Element) -> Element {
+        let bits = element.lsb(DEPTH - 1);
+        let mut tree = &self.tree;
+
+        for bit in bits.iter() {
+            match tree {
+                Node::Parent { left, right, .. } => match *bit {
+                    false => tree = left,
+                    true => tree = right,
+                },
+                Node::Empty { .. } => return Element::NULL_HASH,
+                Node::Leaf(_) => unreachable!("leaves only exist at depth 1"),
+            }
+        }
+
+        match tree {
+            Node::Leaf(occupant) => *occupant,
+            Node::Empty { .. } => Element::NULL_HASH,
+            Node::Parent { .. } => unreachable!("parents never exist at depth 1"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_strategy::proptest;
+
+    use super::*;
+
+    #[proptest]
+    fn exclusion_proof_exists_iff_absent(tree: Tree<64, i32>, element: Element) {
+        let proof = tree.exclusion_proof(element);
+
+        let absent = element != Element::NULL_HASH && !tree.contains_element(&element);
+        assert_eq!(proof.is_some(), absent);
+    }
+
+    #[proptest]
+    fn exclusion_proofs_verify(tree: Tree<16, i32>, element: Element) {
+        if let Some(proof) = tree.exclusion_proof(element) {
+            assert!(proof.verify());
+            assert_eq!(proof.actual_root_hash(), tree.root_hash());
+        }
+    }
+
+    #[test]
+    fn occupied_slot_example() {
+        let mut tree = Tree::<64, i32>::new();
+        tree.insert(Element::new(1), 1).unwrap();
+
+        let collides = Element::ONE + (Element::ONE << 100);
+        let proof = tree.exclusion_proof(collides).unwrap();
+
+        assert_eq!(proof.occupant(), Some(Element::ONE));
+        assert!(proof.verify());
+
+        // pretending the slot is empty doesn't produce the right root hash
+        let forged = ExclusionProof {
+            occupant: Element::NULL_HASH,
+            ..proof
+        };
+        assert!(!forged.verify());
+    }
+
+    #[test]
+    fn proof_for_present_element_does_not_verify() {
+        let mut tree = Tree::<64, i32>::new();
+        tree.insert(Element::new(1), 1).unwrap();
+
+        let forged = ExclusionProof {
+            path: tree.path_for(Element::ONE),
+            occupant: Element::NULL_HASH,
+        };
+        assert!(!forged.verify());
+    }
+}
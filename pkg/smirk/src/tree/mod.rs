@@ -2,10 +2,12 @@ use crate::{
     hash_cache::{HashCache, NoopHashCache},
     Element,
 };
+use bitvec::{prelude::Msb0, vec::BitVec};
 use std::collections::BTreeMap;
 
 mod batch;
 mod error;
+mod exclusion;
 mod insert;
 mod iter;
 mod known_hashes;
@@ -14,6 +16,7 @@ mod raw_api;
 mod tree_repr;
 
 pub use error::{Collision, CollisionError};
+pub use exclusion::ExclusionProof;
 pub use iter::{Elements, IntoIter, Iter};
 pub use path::Path;
 
@@ -227,4 +230,42 @@ where
         self.tree
             .hash_with::<DEPTH, C>(self.cache(), extra_elements.to_vec())
     }
+
+    /// Compute what the root hash would be if all of `removed_elements` were not in the tree
+    ///
+    /// Elements in `removed_elements` which are not in the tree are ignored
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// let mut tree = Tree::<64, ()>::new();
+    /// let hash_before = tree.root_hash();
+    ///
+    /// tree.insert(Element::new(1), ());
+    ///
+    /// assert_eq!(tree.root_hash_without(&[Element::new(1)]), hash_before);
+    /// ```
+    #[inline]
+    #[must_use]
+    #[tracing::instrument(skip(self))]
+    pub fn root_hash_without(&self, removed_elements: &[Element]) -> Element {
+        let (elements, bits) = self.sorted_with_bits(removed_elements);
+        self.tree.hash_without(self.cache(), &elements, &bits, 0)
+    }
+
+    /// Filter `elements` to those present in the tree, and sort them (and their bits) by the bits
+    pub(crate) fn sorted_with_bits(
+        &self,
+        elements: &[Element],
+    ) -> (Vec<Element>, Vec<BitVec<u8, Msb0>>) {
+        let mut elements_with_bits = elements
+            .iter()
+            .filter(|e| self.contains_element(e))
+            .map(|e| (*e, e.lsb(DEPTH - 1).to_bitvec()))
+            .collect::<Vec<_>>();
+
+        elements_with_bits.sort_unstable_by(|(_, a_bits), (_, b_bits)| a_bits.cmp(b_bits));
+        elements_with_bits.dedup_by(|(a, _), (b, _)| a == b);
+
+        elements_with_bits.into_iter().unzip()
+    }
 }
@@ -1,6 +1,6 @@
 use std::iter::zip;
 
-use crate::{Element, Lsb, Tree};
+use crate::{hash_cache::HashCache, Element, Lsb, Tree};
 
 use super::tree_repr::Node;
 
@@ -262,11 +262,97 @@ impl<const DEPTH: usize, V, C> Tree<DEPTH, V, C> {
             root_hash: self.root_hash(),
         }
     }
+
+    /// Generate a [`Path`] for `element` as if all of `removed_elements` were not in the tree
+    ///
+    /// This can be used to generate paths against an older version of the tree, by "removing"
+    /// the elements that have been inserted since. Elements in `removed_elements` which are not in
+    /// the tree are ignored
+    ///
+    /// ```rust
+    /// # use smirk::*;
+    /// let mut tree: Tree<64, _> = smirk! { 1, 2 };
+    /// let old_tree = tree.clone();
+    ///
+    /// tree.insert(Element::new(3), ()).unwrap();
+    ///
+    /// let path = tree.path_for_without(Element::new(1), &[Element::new(3)]);
+    /// assert_eq!(path.actual_root_hash(), old_tree.root_hash());
+    /// assert!(path.proves(Element::new(1)));
+    /// ```
+    #[must_use]
+    pub fn path_for_without(&self, element: Element, removed_elements: &[Element]) -> Path<DEPTH>
+    where
+        C: HashCache,
+    {
+        let (removed, removed_bits) = self.sorted_with_bits(removed_elements);
+        let mut removed = &removed[..];
+        let mut removed_bits = &removed_bits[..];
+
+        let bits = element.lsb(DEPTH - 1);
+
+        let mut siblings = [Element::NULL_HASH; DEPTH];
+        let mut tree = &self.tree;
+
+        for (index, bit) in bits.iter().enumerate() {
+            match tree {
+                Node::Parent { left, right, .. } => {
+                    let right_start = removed_bits
+                        .iter()
+                        .position(|b| b[index])
+                        .unwrap_or(removed_bits.len());
+                    let (lefts, rights) = removed.split_at(right_start);
+                    let (lefts_bits, rights_bits) = removed_bits.split_at(right_start);
+
+                    let (sibling, sibling_removed, sibling_removed_bits) = match *bit {
+                        false => {
+                            tree = left;
+                            (removed, removed_bits) = (lefts, lefts_bits);
+                            (right, rights, rights_bits)
+                        }
+                        true => {
+                            tree = right;
+                            (removed, removed_bits) = (rights, rights_bits);
+                            (left, lefts, lefts_bits)
+                        }
+                    };
+
+                    siblings[index] = sibling.hash_without(
+                        self.cache(),
+                        sibling_removed,
+                        sibling_removed_bits,
+                        index + 1,
+                    );
+                }
+                // empty nodes contain no elements, so there's nothing to remove
+                Node::Empty { depth } => {
+                    for (i, depth) in (1..*depth).rev().enumerate() {
+                        siblings[index + i] = Node::Empty { depth }.hash();
+                    }
+
+                    break;
+                }
+                Node::Leaf(_) => panic!("uh oh"),
+            }
+        }
+
+        // set the last element
+        *siblings.last_mut().unwrap() = element;
+
+        // reverse the siblings so they are in depth-first order
+        siblings[0..DEPTH - 1].reverse();
+
+        Path {
+            siblings,
+            root_hash: self.root_hash_without(removed_elements),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use proptest::prop_assume;
     use test_strategy::proptest;
 
     use super::*;
@@ -316,6 +402,26 @@ mod tests {
         assert_eq!(path.siblings_deepest_first().len(), 63);
     }
 
+    #[proptest]
+    fn path_for_without_matches_old_tree(
+        tree: Tree<16, i32>,
+        batch: crate::Batch<16, i32>,
+        element: Element,
+    ) {
+        let mut new_tree = tree.clone();
+        let inserted = batch.elements().collect::<Vec<_>>();
+
+        let result = new_tree.insert_batch(batch, |_| {}, |_| {});
+        prop_assume!(result.is_ok());
+
+        let old_path = tree.path_for(element);
+        let new_path = new_tree.path_for_without(element, &inserted);
+
+        assert_eq!(new_path.siblings, old_path.siblings);
+        assert_eq!(new_path.actual_root_hash(), tree.root_hash());
+        assert_eq!(new_tree.root_hash_without(&inserted), tree.root_hash());
+    }
+
     #[proptest]
     fn lsb_and_siblings_same_size(tree: Tree<16, i32>, element: Element) {
         let path = tree.path_for(element);
@@ -109,6 +109,49 @@ impl Node {
         }
     }
 
+    /// Compute the hash of this node as if `removed_elements` were not present
+    ///
+    /// `path_depth` is the number of left/right decisions taken to reach this node, and the
+    /// elements and bits should be sorted by the bits before calling this function
+    pub(crate) fn hash_without<C: HashCache>(
+        &self,
+        cache: &C,
+        removed_elements: &[Element],
+        removed_elements_bits: &[BitVec<u8, Msb0>],
+        path_depth: usize,
+    ) -> Element {
+        if removed_elements.is_empty() {
+            return self.hash();
+        }
+
+        match self {
+            Self::Leaf(element) if removed_elements.contains(element) => empty_tree_hash(1),
+            Self::Leaf(element) => *element,
+            Self::Empty { .. } => self.hash(),
+            Self::Parent { left, right, .. } => {
+                let right_start = removed_elements_bits
+                    .iter()
+                    .position(|b| b[path_depth])
+                    .unwrap_or(removed_elements_bits.len());
+
+                let left_hash = left.hash_without(
+                    cache,
+                    &removed_elements[..right_start],
+                    &removed_elements_bits[..right_start],
+                    path_depth + 1,
+                );
+                let right_hash = right.hash_without(
+                    cache,
+                    &removed_elements[right_start..],
+                    &removed_elements_bits[right_start..],
+                    path_depth + 1,
+                );
+
+                cache.hash(left_hash, right_hash)
+            }
+        }
+    }
+
     pub fn hash(&self) -> Element {
         match self {
             Self::Leaf(hash) | Self::Parent { hash, .. } => *hash,
@@ -0,0 +1,180 @@
+use core::fmt;
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// A range of IP addresses in CIDR notation, e.g. `10.0.0.0/8`
+///
+/// A plain IP address is a range containing just that address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNet {
+    /// Create a range from an address and prefix length, the host bits of `addr` are ignored
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self, InvalidIpNet> {
+        let max_prefix_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix_len > max_prefix_len {
+            return Err(InvalidIpNet(format!("{addr}/{prefix_len}")));
+        }
+
+        Ok(Self {
+            addr: mask(addr, prefix_len),
+            prefix_len,
+        })
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(_), IpAddr::V4(_)) | (IpAddr::V6(_), IpAddr::V6(_)) => {
+                mask(ip, self.prefix_len) == self.addr
+            }
+            // IPv4 peers can connect to IPv6 listeners as IPv4-mapped addresses
+            (IpAddr::V4(_), IpAddr::V6(ip6)) => ip6
+                .to_ipv4_mapped()
+                .map_or(false, |ip4| self.contains(IpAddr::V4(ip4))),
+            (IpAddr::V6(_), IpAddr::V4(_)) => false,
+        }
+    }
+}
+
+fn mask(addr: IpAddr, prefix_len: u8) -> IpAddr {
+    match addr {
+        IpAddr::V4(ip4) => {
+            let mask = u32::MAX
+                .checked_shl(32 - u32::from(prefix_len))
+                .unwrap_or(0);
+            IpAddr::V4(Ipv4Addr::from(u32::from(ip4) & mask))
+        }
+        IpAddr::V6(ip6) => {
+            let mask = u128::MAX
+                .checked_shl(128 - u32::from(prefix_len))
+                .unwrap_or(0);
+            IpAddr::V6(Ipv6Addr::from(u128::from(ip6) & mask))
+        }
+    }
+}
+
+impl From<IpAddr> for IpNet {
+    fn from(addr: IpAddr) -> Self {
+        let prefix_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        Self { addr, prefix_len }
+    }
+}
+
+impl FromStr for IpNet {
+    type Err = InvalidIpNet;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidIpNet(s.to_string());
+
+        match s.split_once('/') {
+            Some((addr, prefix_len)) => {
+                let addr = addr.parse().map_err(|_| invalid())?;
+                let prefix_len = prefix_len.parse().map_err(|_| invalid())?;
+                Self::new(addr, prefix_len).map_err(|_| invalid())
+            }
+            None => Ok(Self::from(s.parse::<IpAddr>().map_err(|_| invalid())?)),
+        }
+    }
+}
+
+impl TryFrom<String> for IpNet {
+    type Error = InvalidIpNet;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<IpNet> for String {
+    fn from(net: IpNet) -> Self {
+        net.to_string()
+    }
+}
+
+impl fmt::Display for IpNet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidIpNet(String);
+
+impl fmt::Display for InvalidIpNet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid IP address or CIDR range: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidIpNet {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let net: IpNet = "10.1.2.3/8".parse().unwrap();
+        assert_eq!(net.addr(), ip("10.0.0.0"));
+        assert_eq!(net.prefix_len(), 8);
+        assert_eq!(net.to_string(), "10.0.0.0/8");
+
+        let net: IpNet = "10.1.2.3".parse().unwrap();
+        assert_eq!(net.to_string(), "10.1.2.3/32");
+
+        let net: IpNet = "fd00::1/16".parse().unwrap();
+        assert_eq!(net.to_string(), "fd00::/16");
+
+        assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+        assert!("10.0.0/8".parse::<IpNet>().is_err());
+        assert!("10.0.0.0/".parse::<IpNet>().is_err());
+    }
+
+    #[test]
+    fn contains() {
+        let net: IpNet = "192.168.0.0/16".parse().unwrap();
+        assert!(net.contains(ip("192.168.0.1")));
+        assert!(net.contains(ip("192.168.255.255")));
+        assert!(!net.contains(ip("192.169.0.1")));
+        assert!(net.contains(ip("::ffff:192.168.1.1")));
+        assert!(!net.contains(ip("fd00::1")));
+
+        let net: IpNet = "0.0.0.0/0".parse().unwrap();
+        assert!(net.contains(ip("1.2.3.4")));
+
+        let net: IpNet = "fd00::/8".parse().unwrap();
+        assert!(net.contains(ip("fd12::1")));
+        assert!(!net.contains(ip("fe80::1")));
+        assert!(!net.contains(ip("10.0.0.1")));
+
+        let net: IpNet = "1.2.3.4".parse().unwrap();
+        assert!(net.contains(ip("1.2.3.4")));
+        assert!(!net.contains(ip("1.2.3.5")));
+    }
+}
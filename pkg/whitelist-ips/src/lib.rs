@@ -1,29 +1,57 @@
 use core::fmt;
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     net::IpAddr,
     task::{Context, Poll, Waker},
 };
 
 use libp2p::{
+    core::{ConnectedPoint, Endpoint},
     multiaddr::Protocol,
     swarm::{
-        dummy, CloseConnection, ConnectionDenied, ConnectionId, NetworkBehaviour, PollParameters,
-        THandler, THandlerInEvent, ToSwarm,
+        dummy, CloseConnection, ConnectionClosed, ConnectionDenied, ConnectionEstablished,
+        ConnectionId, FromSwarm, NetworkBehaviour, PollParameters, THandler, THandlerInEvent,
+        ToSwarm,
     },
     Multiaddr, PeerId,
 };
 
-#[derive(Debug, Clone, Default)]
+mod ip_net;
+
+pub use ip_net::{InvalidIpNet, IpNet};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 #[non_exhaustive]
 pub struct Config {
-    pub whitelisted_ips: HashSet<IpAddr>,
+    /// Inbound connections are only allowed from these ranges. If empty, inbound connections
+    /// are allowed from any IP that isn't denied
+    pub whitelisted_ips: HashSet<IpNet>,
+
+    /// Connections to or from these ranges are always denied
+    pub denied_ips: HashSet<IpNet>,
+}
+
+impl Config {
+    fn allows(&self, ip: IpAddr, endpoint: Endpoint) -> bool {
+        if self.denied_ips.iter().any(|net| net.contains(ip)) {
+            return false;
+        }
+
+        // We only dial addresses we chose to, so the allow list only applies to inbound
+        // connections
+        endpoint == Endpoint::Dialer
+            || self.whitelisted_ips.is_empty()
+            || self.whitelisted_ips.iter().any(|net| net.contains(ip))
+    }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct Behaviour {
     config: Config,
-    close_connections: VecDeque<PeerId>,
+    /// The remote IP of each established connection, so they can be rechecked when the config
+    /// changes
+    connections: HashMap<ConnectionId, (PeerId, IpAddr, Endpoint)>,
+    close_connections: VecDeque<(PeerId, ConnectionId)>,
     waker: Option<Waker>,
 }
 
@@ -39,12 +67,53 @@ impl Behaviour {
         }
     }
 
-    pub fn whitelisted_ips(&self) -> &HashSet<IpAddr> {
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn whitelisted_ips(&self) -> &HashSet<IpNet> {
         &self.config.whitelisted_ips
     }
 
-    pub fn whitelisted_ips_mut(&mut self) -> &mut HashSet<IpAddr> {
-        &mut self.config.whitelisted_ips
+    pub fn denied_ips(&self) -> &HashSet<IpNet> {
+        &self.config.denied_ips
+    }
+
+    /// Replace the config, closing any connections it no longer allows
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+
+        for (connection, (peer, ip, endpoint)) in &self.connections {
+            if !self.config.allows(*ip, *endpoint) {
+                tracing::info!(%peer, %ip, "Closing connection that is no longer allowed");
+                self.close_connections.push_back((*peer, *connection));
+            }
+        }
+
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn check(
+        &self,
+        peer: PeerId,
+        addr: &Multiaddr,
+        endpoint: Endpoint,
+    ) -> Result<(), ConnectionDenied> {
+        let allowed = match ip(addr) {
+            Some(ip) => self.config.allows(ip, endpoint),
+            // e.g. a `/dns` address we dialed, which can't be checked against IP ranges
+            None => endpoint == Endpoint::Dialer || self.config.whitelisted_ips.is_empty(),
+        };
+        if !allowed {
+            return Err(ConnectionDenied::new(NotAllowed {
+                peer,
+                addr: addr.clone(),
+            }));
+        }
+
+        Ok(())
     }
 }
 
@@ -52,8 +121,28 @@ impl NetworkBehaviour for Behaviour {
     type ConnectionHandler = libp2p::swarm::dummy::ConnectionHandler;
     type OutEvent = ();
 
-    fn on_swarm_event(&mut self, _event: libp2p::swarm::FromSwarm<Self::ConnectionHandler>) {
-        // do nothing
+    fn on_swarm_event(&mut self, event: FromSwarm<Self::ConnectionHandler>) {
+        match event {
+            FromSwarm::ConnectionEstablished(ConnectionEstablished {
+                peer_id,
+                connection_id,
+                endpoint,
+                ..
+            }) => {
+                let endpoint_kind = match endpoint {
+                    ConnectedPoint::Dialer { .. } => Endpoint::Dialer,
+                    ConnectedPoint::Listener { .. } => Endpoint::Listener,
+                };
+                if let Some(ip) = ip(endpoint.get_remote_address()) {
+                    self.connections
+                        .insert(connection_id, (peer_id, ip, endpoint_kind));
+                }
+            }
+            FromSwarm::ConnectionClosed(ConnectionClosed { connection_id, .. }) => {
+                self.connections.remove(&connection_id);
+            }
+            _ => {}
+        }
     }
 
     fn on_connection_handler_event(
@@ -80,13 +169,18 @@ impl NetworkBehaviour for Behaviour {
         _local_addr: &Multiaddr,
         remote_addr: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
-        if !allowed(remote_addr, &self.config.whitelisted_ips) {
-            return Err(ConnectionDenied::new(NotAllowed {
-                peer,
-                addr: remote_addr.clone(),
-            }));
-        }
+        self.check(peer, remote_addr, Endpoint::Listener)?;
+        Ok(dummy::ConnectionHandler)
+    }
 
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        _role_override: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.check(peer, addr, Endpoint::Dialer)?;
         Ok(dummy::ConnectionHandler)
     }
 
@@ -95,10 +189,10 @@ impl NetworkBehaviour for Behaviour {
         cx: &mut Context<'_>,
         _: &mut impl PollParameters,
     ) -> Poll<ToSwarm<Self::OutEvent, THandlerInEvent<Self>>> {
-        if let Some(peer) = self.close_connections.pop_front() {
+        if let Some((peer, connection)) = self.close_connections.pop_front() {
             return Poll::Ready(ToSwarm::CloseConnection {
                 peer_id: peer,
-                connection: CloseConnection::All,
+                connection: CloseConnection::One(connection),
             });
         }
 
@@ -107,14 +201,12 @@ impl NetworkBehaviour for Behaviour {
     }
 }
 
-fn allowed(multiaddr: &Multiaddr, ips: &HashSet<IpAddr>) -> bool {
-    let ip = match multiaddr.iter().next() {
-        Some(Protocol::Ip4(ip4)) => IpAddr::V4(ip4),
-        Some(Protocol::Ip6(ip6)) => IpAddr::V6(ip6),
-        _other => return false,
-    };
-
-    ips.contains(&ip)
+fn ip(multiaddr: &Multiaddr) -> Option<IpAddr> {
+    match multiaddr.iter().next() {
+        Some(Protocol::Ip4(ip4)) => Some(IpAddr::V4(ip4)),
+        Some(Protocol::Ip6(ip6)) => Some(IpAddr::V6(ip6)),
+        _other => None,
+    }
 }
 
 #[derive(Debug)]
@@ -127,10 +219,39 @@ impl fmt::Display for NotAllowed {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "peer {} (at addr {}) is not in the allow list",
+            "peer {} (at addr {}) is not allowed by the IP filter",
             self.peer, self.addr
         )
     }
 }
 
 impl std::error::Error for NotAllowed {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(nets: &[&str]) -> HashSet<IpNet> {
+        nets.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn allows() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+
+        let config = Config::default();
+        assert!(config.allows(ip("1.2.3.4"), Endpoint::Listener));
+
+        let config = Config {
+            whitelisted_ips: nets(&["10.0.0.0/8"]),
+            denied_ips: nets(&["10.0.0.13", "192.168.0.0/16"]),
+        };
+        assert!(config.allows(ip("10.1.2.3"), Endpoint::Listener));
+        assert!(!config.allows(ip("1.2.3.4"), Endpoint::Listener));
+        assert!(!config.allows(ip("10.0.0.13"), Endpoint::Listener));
+
+        // The allow list only applies to inbound connections, the deny list applies to both
+        assert!(config.allows(ip("1.2.3.4"), Endpoint::Dialer));
+        assert!(!config.allows(ip("192.168.1.1"), Endpoint::Dialer));
+    }
+}
@@ -37,6 +37,51 @@ impl SnarkWitnessV1 {
             .collect()
     }
 
+    /// Verify many proofs for the same circuit, accumulating their KZG checks so that only a
+    /// single multi-pairing is computed for the whole batch, instead of one per proof.
+    ///
+    /// Returns `false` if any proof is invalid, without identifying which one, so callers should
+    /// fall back to [`SnarkWitnessV1::verify`] to find the invalid proof
+    pub fn verify_batch<'a>(
+        witnesses: impl IntoIterator<Item = &'a Self>,
+        kind: CircuitKind,
+    ) -> bool {
+        let params = load_params(kind.params());
+        let vk = kind.vk();
+
+        // Each proof's accumulator is scaled by a random challenge before it is combined with the
+        // others, so invalid proofs can't cancel each other out
+        let mut strategy = AccumulatorStrategy::new(params.verifier_params());
+        for witness in witnesses {
+            let mut transcript =
+                PoseidonTranscript::<NativeLoader, _>::init(Cursor::new(witness.proof.clone()));
+
+            strategy = match verify_proof::<
+                _,
+                VerifierSHPLONK<_>,
+                _,
+                PoseidonTranscript<NativeLoader, _>,
+                _,
+            >(
+                params.verifier_params(),
+                vk,
+                strategy,
+                &[&witness
+                    .fr_instances()
+                    .iter()
+                    .map(|v| v.as_slice())
+                    .collect::<Vec<_>>()],
+                &mut transcript,
+            ) {
+                Ok(strategy) => strategy,
+                // The proof is malformed
+                Err(_) => return false,
+            };
+        }
+
+        VerificationStrategy::<_, VerifierSHPLONK<_>>::finalize(strategy)
+    }
+
     pub fn verify(&self, kind: CircuitKind) -> bool {
         let params = load_params(kind.params());
         let vk = kind.vk();
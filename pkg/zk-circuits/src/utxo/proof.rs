@@ -112,6 +112,18 @@ impl<const MERKLE_D: usize> UTXOProof<MERKLE_D> {
             SnarkWitness::V1(sw) => sw.verify(CircuitKind::Utxo),
         }
     }
+
+    /// Verify many proofs at once, see [`SnarkWitnessV1::verify_batch`]
+    pub fn verify_batch<'a>(proofs: impl IntoIterator<Item = &'a Self>) -> bool {
+        let witnesses = proofs
+            .into_iter()
+            .map(|proof| match proof.to_snark_witness() {
+                SnarkWitness::V1(sw) => sw,
+            })
+            .collect::<Vec<_>>();
+
+        SnarkWitnessV1::verify_batch(&witnesses, CircuitKind::Utxo)
+    }
 }
 
 #[cfg(test)]
@@ -143,6 +155,27 @@ mod tests {
         println!("{}", serde_json::to_string(&snark_witness).unwrap());
     }
 
+    #[test]
+    fn verify_batch() {
+        let u = Utxo::<161>::new(
+            [InputNote::padding_note(), InputNote::padding_note()],
+            [Note::padding_note(), Note::padding_note()],
+            smirk::Tree::<161, ()>::new().root_hash(),
+            UtxoKind::Transfer,
+        );
+
+        let snark = u.snark(CircuitKind::Utxo).unwrap();
+        let valid = UTXOProof::<161>::from_snark_witness(SnarkWitness::V1(snark.to_witness()));
+
+        assert!(UTXOProof::<161>::verify_batch([]));
+        assert!(UTXOProof::<161>::verify_batch([&valid, &valid]));
+
+        let mut invalid = valid.clone();
+        invalid.recent_root = Element::new(1);
+        assert!(!invalid.verify());
+        assert!(!UTXOProof::<161>::verify_batch([&valid, &invalid, &valid]));
+    }
+
     #[test]
     fn bench_txn_hashing() {
         let txn = UTXOProof::<MERKLE_TREE_DEPTH>::new(